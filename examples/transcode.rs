@@ -0,0 +1,24 @@
+use msedge_tts::{
+    transcode::{transcode_to_file, TranscodeFormat},
+    tts::{client::connect, SpeechConfig},
+    voice::get_voices_list,
+};
+
+fn main() {
+    println!("get voices list...");
+    let voices = get_voices_list().unwrap();
+    for voice in &voices {
+        if voice.name.contains("YunyangNeural") {
+            println!("choose '{}' to synthesize...", voice.name);
+            let config = SpeechConfig::from(voice);
+            let mut tts = connect().unwrap();
+            let audio = tts
+                .synthesize("Hello, World! 你好，世界！", &config)
+                .unwrap();
+            let path = format!("hello.{}", TranscodeFormat::Flac.extension());
+            transcode_to_file(&audio, TranscodeFormat::Flac, &path).unwrap();
+            println!("write '{}'", path);
+            break;
+        }
+    }
+}
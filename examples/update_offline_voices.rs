@@ -0,0 +1,14 @@
+use msedge_tts::voice::get_voices_list;
+use std::{fs::File, io::Write};
+
+// regenerate `data/voices.json.gz` from the list endpoint and record the date of the run in `data/voices.date`
+fn main() {
+    let voices = get_voices_list().unwrap();
+    let file = File::create("data/voices.json.gz").unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::best());
+    serde_json::to_writer(&mut encoder, &voices).unwrap();
+    encoder.finish().unwrap().flush().unwrap();
+    let date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    std::fs::write("data/voices.date", &date).unwrap();
+    println!("saved {} voices of {}", voices.len(), date);
+}
@@ -8,3 +8,46 @@ pub static SEC_FETCH_DEST: &str = "empty";
 pub static USER_AGENT:&str="Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 Edg/120.0.0.0";
 pub static WSS_URL:&str="wss://speech.platform.bing.com/consumer/speech/synthesize/readaloud/edge/v1?TrustedClientToken=6A5AA1D4EAFF4E9FB37E23D68491D6F4&ConnectionId=";
 pub static ORIGIN: &str = "chrome-extension://jdiccldimpdaibmpdkjnbmckianbfold";
+pub static SEC_MS_GEC_VERSION: &str = "1-130.0.2849.68";
+/// default neural voice of each locale, the first entry of a language is its default
+pub static DEFAULT_VOICES: &[(&str, &str)] = &[
+    ("ar-SA", "HamedNeural"),
+    ("ca-ES", "JoanaNeural"),
+    ("cs-CZ", "VlastaNeural"),
+    ("da-DK", "ChristelNeural"),
+    ("de-DE", "KatjaNeural"),
+    ("el-GR", "AthinaNeural"),
+    ("en-US", "AriaNeural"),
+    ("en-AU", "NatashaNeural"),
+    ("en-CA", "ClaraNeural"),
+    ("en-GB", "SoniaNeural"),
+    ("en-IN", "NeerjaNeural"),
+    ("es-ES", "ElviraNeural"),
+    ("es-MX", "DaliaNeural"),
+    ("fi-FI", "NooraNeural"),
+    ("fr-FR", "DeniseNeural"),
+    ("fr-CA", "SylvieNeural"),
+    ("he-IL", "HilaNeural"),
+    ("hi-IN", "SwaraNeural"),
+    ("hu-HU", "NoemiNeural"),
+    ("id-ID", "GadisNeural"),
+    ("it-IT", "ElsaNeural"),
+    ("ja-JP", "NanamiNeural"),
+    ("ko-KR", "SunHiNeural"),
+    ("nb-NO", "PernilleNeural"),
+    ("nl-NL", "ColetteNeural"),
+    ("pl-PL", "ZofiaNeural"),
+    ("pt-BR", "FranciscaNeural"),
+    ("pt-PT", "RaquelNeural"),
+    ("ro-RO", "AlinaNeural"),
+    ("ru-RU", "SvetlanaNeural"),
+    ("sk-SK", "ViktoriaNeural"),
+    ("sv-SE", "SofieNeural"),
+    ("th-TH", "PremwadeeNeural"),
+    ("tr-TR", "EmelNeural"),
+    ("uk-UA", "PolinaNeural"),
+    ("vi-VN", "HoaiMyNeural"),
+    ("zh-CN", "XiaoxiaoNeural"),
+    ("zh-HK", "HiuGaaiNeural"),
+    ("zh-TW", "HsiaoChenNeural"),
+];
@@ -8,14 +8,79 @@ pub type Result<T, E = Error> = std::result::Result<T, E>;
 pub enum Error {
     #[error("unexpected message: {0}")]
     UnexpectedMessage(String),
+    #[cfg(feature = "http-isahc")]
     #[error("isahc error: {0}")]
     IsahcError(#[from] isahc::Error),
+    #[cfg(feature = "http-ureq")]
+    #[error("ureq error: {0}")]
+    UreqError(#[from] Box<ureq::Error>),
+    #[cfg(any(feature = "http-reqwest", feature = "tokio"))]
+    #[error("reqwest error: {0}")]
+    ReqwestError(#[from] reqwest::Error),
     #[error("tungstenite error: {0}")]
     TungsteniteError(#[from] tungstenite::Error),
+    #[error("tls error: {0}")]
+    TlsError(#[from] native_tls::Error),
     #[error("serde json error: {0}")]
     SerdeJsonError(#[from] serde_json::Error),
     #[error("proxy error: {0}")]
     ProxyError(#[from] ProxyError),
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("unexpected http status: {0}")]
+    HttpStatus(u16),
+    #[error("invalid locale: {0}")]
+    InvalidLocale(String),
+    #[error("voice not found: {0}")]
+    VoiceNotFound(String),
+    #[error("invalid speech config `{field}`: {reason}")]
+    InvalidSpeechConfig { field: &'static str, reason: String },
+    #[error("invalid audio format `{format}`{}", if suggestions.is_empty() { String::new() } else { format!(", did you mean: {}", suggestions.join(", ")) })]
+    InvalidAudioFormat {
+        format: String,
+        /// closest known formats
+        suggestions: Vec<String>,
+    },
+    #[error("ssml of {actual} bytes is longer than the limit of {limit} bytes")]
+    TextTooLong { limit: usize, actual: usize },
+    #[error("invalid ssml: {0}")]
+    InvalidSsml(String),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("a previous synthesis was abandoned before its end and the client cannot reconnect")]
+    TurnAbandoned,
+    #[error("synthesis incomplete after {} audio bytes: {error}", partial.audio_bytes.len())]
+    IncompleteSynthesis {
+        /// audio and metadata received before the error
+        partial: Box<crate::tts::client::SynthesizedAudio>,
+        #[source]
+        error: Box<Error>,
+    },
+    #[error("synthesis timed out{}", partial.as_ref().map(|partial| format!(" after {} audio bytes", partial.audio_bytes.len())).unwrap_or_default())]
+    Timeout {
+        /// audio and metadata received before the timeout
+        partial: Option<Box<crate::tts::client::SynthesizedAudio>>,
+    },
+    #[error("ffmpeg error: {0}")]
+    FfmpegError(String),
+    #[cfg(feature = "tokio-compat")]
+    #[error("tokio join error: {0}")]
+    TokioJoinError(#[from] tokio::task::JoinError),
+}
+
+impl Error {
+    /// `X-RequestId` of a synthesis which failed after receiving audio.
+    ///
+    /// The client keeps the id of any failed synthesis, see [request_id](crate::tts::client::MSEdgeTTSClient::request_id).
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Error::IncompleteSynthesis { partial, .. }
+            | Error::Timeout {
+                partial: Some(partial),
+            } => Some(&partial.request_id),
+            _ => None,
+        }
+    }
 }
 
 /// Proxy Error
@@ -205,8 +205,22 @@
 //!     }
 //!     ```
 
+// headers and url of the voice list are unused without an HTTP backend
+#[cfg_attr(
+    not(any(
+        feature = "http-isahc",
+        feature = "http-ureq",
+        feature = "http-reqwest",
+        feature = "tokio"
+    )),
+    allow(dead_code)
+)]
 mod constants;
 
 pub mod error;
+pub mod retry;
+pub mod ssml;
+#[cfg(feature = "ffmpeg")]
+pub mod transcode;
 pub mod tts;
 pub mod voice;
@@ -0,0 +1,127 @@
+//! Retry policy shared by network operations of this crate
+//!
+//! Only [transient](Error::is_transient) errors are retried, with exponential backoff between attempts.
+
+use crate::error::{Error, Result};
+use std::time::Duration;
+
+/// Retry attempts and exponential backoff
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one, `1` disables retry
+    pub max_attempts: u32,
+    /// Delay before the first retry
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay
+    pub max_backoff: Duration,
+    /// Delay multiplier after each retry
+    pub multiplier: f64,
+    /// Randomize each delay between half and the full value, so clients do not retry in lockstep
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, backoff from 500ms doubling up to 10s, with jitter
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Do not retry
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Retry of websocket handshakes rejected with 403: 3 attempts, backoff from 200ms up to 2s, with jitter
+    pub fn forbidden() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(2),
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+
+    /// Delay before retry number `retry`, starting at 0
+    pub fn backoff(&self, retry: u32) -> Duration {
+        // clamp in f64, `Duration::mul_f64` panics on overflow
+        let backoff = Duration::from_secs_f64(
+            (self.initial_backoff.as_secs_f64()
+                * self.multiplier.max(1.0).powi(retry.min(64) as i32))
+            .min(self.max_backoff.as_secs_f64()),
+        );
+        if self.jitter {
+            // uuid v4 is already backed by a fast rng
+            let random = (uuid::Uuid::new_v4().as_u128() as u64) as f64 / u64::MAX as f64;
+            backoff.mul_f64(0.5 + random / 2.0)
+        } else {
+            backoff
+        }
+    }
+
+    /// Run `f` until it succeeds, fails with a non-transient error or attempts run out
+    pub fn retry<T>(&self, mut f: impl FnMut() -> Result<T>) -> Result<T> {
+        let mut retry = 0;
+        loop {
+            match f() {
+                Err(error) if error.is_transient() && retry + 1 < self.max_attempts => {
+                    std::thread::sleep(self.backoff(retry));
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Run `f` asynchronously until it succeeds, fails with a non-transient error or attempts run out
+    pub async fn retry_async<T, F, Fut>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut retry = 0;
+        loop {
+            match f().await {
+                Err(error) if error.is_transient() && retry + 1 < self.max_attempts => {
+                    async_io::Timer::after(self.backoff(retry)).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Whether the error may go away by retrying: network errors, 5xx and 429 responses
+    pub fn is_transient(&self) -> bool {
+        match self {
+            #[cfg(feature = "http-isahc")]
+            Error::IsahcError(error) => error.is_network() || error.is_timeout(),
+            #[cfg(feature = "http-ureq")]
+            Error::UreqError(error) => matches!(**error, ureq::Error::Transport(_)),
+            #[cfg(any(feature = "http-reqwest", feature = "tokio"))]
+            Error::ReqwestError(error) => error.is_connect() || error.is_timeout(),
+            Error::TungsteniteError(tungstenite::Error::Io(_))
+            | Error::TungsteniteError(tungstenite::Error::ConnectionClosed)
+            | Error::TungsteniteError(tungstenite::Error::AlreadyClosed) => true,
+            Error::TungsteniteError(tungstenite::Error::Http(response)) => {
+                response.status().is_server_error() || response.status().as_u16() == 429
+            }
+            Error::IoError(_) => true,
+            Error::HttpStatus(status) => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+}
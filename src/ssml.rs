@@ -0,0 +1,308 @@
+//! SSML Builder
+//!
+//! Build SSML documents accepted by the **MSEdge Read aloud** API without writing XML by hand.
+//! Text is escaped and values are validated when calling [build](Speak::build).
+//! A document may switch voices any number of times, send it with
+//! [synthesize_ssml](crate::tts::client::MSEdgeTTSClient::synthesize_ssml) to get the audio of all voices in one turn.
+//! ```rust
+//! use msedge_tts::ssml::{Prosody, Speak};
+//! use msedge_tts::tts::Rate;
+//!
+//! let ssml = Speak::new()
+//!     .voice("en-US-AriaNeural")
+//!     .text("Hello,")
+//!     .break_ms(500)
+//!     .prosody(Prosody {
+//!         rate: Rate::Slow,
+//!         ..Default::default()
+//!     })
+//!     .text("World!")
+//!     .build()
+//!     .unwrap();
+//! ```
+
+use crate::{
+    error::{Error, Result},
+    tts::{Pitch, Rate, Volume},
+};
+
+/// Longest break the service accepts, in milliseconds
+pub const MAX_BREAK_MS: u32 = 5000;
+
+/// Prosody applied to text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Prosody {
+    pub pitch: Pitch,
+    pub rate: Rate,
+    pub volume: Volume,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Voice(String),
+    Prosody(Prosody),
+    Text(String),
+    Break(u32),
+    Phoneme {
+        text: String,
+        ph: String,
+    },
+    Lexicon(String),
+    Audio {
+        src: String,
+        fallback: Option<String>,
+    },
+    Bookmark(String),
+}
+
+/// SSML `<speak>` document builder
+#[derive(Debug, Clone)]
+pub struct Speak {
+    lang: String,
+    nodes: Vec<Node>,
+}
+
+impl Default for Speak {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Speak {
+    /// Create an empty document with `xml:lang='en-US'`
+    pub fn new() -> Self {
+        Self {
+            lang: "en-US".to_owned(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Set `xml:lang` of the document
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = lang.into();
+        self
+    }
+
+    /// Start a `<voice>` element, following content is spoken by this voice
+    pub fn voice(mut self, name: impl Into<String>) -> Self {
+        self.nodes.push(Node::Voice(name.into()));
+        self
+    }
+
+    /// Add an external pronunciation lexicon to the current voice, must come before its content
+    pub fn lexicon(mut self, uri: impl Into<String>) -> Self {
+        self.nodes.push(Node::Lexicon(uri.into()));
+        self
+    }
+
+    /// Set prosody of following text in the current voice
+    pub fn prosody(mut self, prosody: Prosody) -> Self {
+        self.nodes.push(Node::Prosody(prosody));
+        self
+    }
+
+    /// Add text, it will be escaped
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.nodes.push(Node::Text(text.into()));
+        self
+    }
+
+    /// Add text pronounced as the IPA phonemes `ph`, e.g. `phoneme("tomato", "təˈmeɪtoʊ")`
+    pub fn phoneme(mut self, text: impl Into<String>, ph: impl Into<String>) -> Self {
+        self.nodes.push(Node::Phoneme {
+            text: text.into(),
+            ph: ph.into(),
+        });
+        self
+    }
+
+    /// Play a pre-recorded audio clip from a http(s) url, `fallback` text is spoken if it can not be played
+    pub fn audio(mut self, src: impl Into<String>, fallback: Option<&str>) -> Self {
+        self.nodes.push(Node::Audio {
+            src: src.into(),
+            fallback: fallback.map(|text| text.to_owned()),
+        });
+        self
+    }
+
+    /// Add a bookmark, the service sends a [Bookmark](crate::tts::AudioMetadata::BOOKMARK_TYPE) metadata entry
+    /// with the audio offset when it is reached
+    pub fn bookmark(mut self, mark: impl Into<String>) -> Self {
+        self.nodes.push(Node::Bookmark(mark.into()));
+        self
+    }
+
+    /// Add a pause, at most [MAX_BREAK_MS]
+    pub fn break_ms(mut self, ms: u32) -> Self {
+        self.nodes.push(Node::Break(ms));
+        self
+    }
+
+    /// Validate and render the document
+    pub fn build(&self) -> Result<String> {
+        let mut ssml = format!(
+            "<speak version='1.0' xmlns='http://www.w3.org/2001/10/synthesis' xmlns:mstts='https://www.w3.org/2001/mstts' xml:lang='{}'>",
+            escape(&self.lang)
+        );
+        let mut in_voice = false;
+        let mut voice_has_content = false;
+        let mut prosody = Prosody::default();
+        for node in &self.nodes {
+            match node {
+                Node::Voice(name) => {
+                    if name.trim().is_empty() {
+                        return Err(Error::InvalidSsml("voice name is empty".to_owned()));
+                    }
+                    if in_voice {
+                        ssml.push_str("</voice>");
+                    }
+                    ssml.push_str(&format!("<voice name='{}'>", escape(name)));
+                    in_voice = true;
+                    voice_has_content = false;
+                    prosody = Prosody::default();
+                }
+                Node::Prosody(new_prosody) => {
+                    new_prosody.pitch.validate()?;
+                    new_prosody.rate.validate()?;
+                    new_prosody.volume.validate()?;
+                    prosody = *new_prosody;
+                }
+                Node::Text(text) => {
+                    if !in_voice {
+                        return Err(Error::InvalidSsml("text outside of a voice".to_owned()));
+                    }
+                    push_content(&mut ssml, &prosody, &escape(text));
+                    voice_has_content = true;
+                }
+                Node::Phoneme { text, ph } => {
+                    if !in_voice {
+                        return Err(Error::InvalidSsml("phoneme outside of a voice".to_owned()));
+                    }
+                    if ph.trim().is_empty() {
+                        return Err(Error::InvalidSsml(format!("empty phonemes of {}", text)));
+                    }
+                    let phoneme = format!(
+                        "<phoneme alphabet='ipa' ph='{}'>{}</phoneme>",
+                        escape(ph),
+                        escape(text)
+                    );
+                    push_content(&mut ssml, &prosody, &phoneme);
+                    voice_has_content = true;
+                }
+                Node::Break(ms) => {
+                    if !in_voice {
+                        return Err(Error::InvalidSsml("break outside of a voice".to_owned()));
+                    }
+                    if *ms > MAX_BREAK_MS {
+                        return Err(Error::InvalidSsml(format!(
+                            "break of {}ms is longer than {}ms",
+                            ms, MAX_BREAK_MS
+                        )));
+                    }
+                    ssml.push_str(&format!("<break time='{}ms'/>", ms));
+                    voice_has_content = true;
+                }
+                Node::Audio { src, fallback } => {
+                    if !in_voice {
+                        return Err(Error::InvalidSsml("audio outside of a voice".to_owned()));
+                    }
+                    if !(src.starts_with("https://") || src.starts_with("http://")) {
+                        return Err(Error::InvalidSsml(format!(
+                            "audio src is not a http(s) url: {}",
+                            src
+                        )));
+                    }
+                    match fallback {
+                        Some(fallback) => ssml.push_str(&format!(
+                            "<audio src='{}'>{}</audio>",
+                            escape(src),
+                            escape(fallback)
+                        )),
+                        None => ssml.push_str(&format!("<audio src='{}'/>", escape(src))),
+                    }
+                    voice_has_content = true;
+                }
+                Node::Bookmark(mark) => {
+                    if !in_voice {
+                        return Err(Error::InvalidSsml("bookmark outside of a voice".to_owned()));
+                    }
+                    if mark.is_empty() {
+                        return Err(Error::InvalidSsml("bookmark mark is empty".to_owned()));
+                    }
+                    ssml.push_str(&format!("<bookmark mark='{}'/>", escape(mark)));
+                    voice_has_content = true;
+                }
+                Node::Lexicon(uri) => {
+                    if !in_voice || voice_has_content {
+                        return Err(Error::InvalidSsml(format!(
+                            "lexicon {} must be at the start of a voice",
+                            uri
+                        )));
+                    }
+                    ssml.push_str(&format!("<lexicon uri='{}'/>", escape(uri)));
+                }
+            }
+        }
+        if !in_voice {
+            return Err(Error::InvalidSsml("no voice".to_owned()));
+        }
+        ssml.push_str("</voice></speak>");
+        Ok(ssml)
+    }
+}
+
+/// Push rendered content, wrapped in `<prosody>` unless prosody is default
+fn push_content(ssml: &mut String, prosody: &Prosody, content: &str) {
+    if *prosody == Prosody::default() {
+        ssml.push_str(content);
+    } else {
+        ssml.push_str(&format!(
+            "<prosody pitch='{}' rate='{}' volume='{}'>{}</prosody>",
+            prosody.pitch, prosody.rate, prosody.volume, content
+        ));
+    }
+}
+
+/// Escape XML special characters in text and attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::escape;
+
+    #[test]
+    fn escape_special_characters() {
+        assert_eq!(escape("a < b > c"), "a &lt; b &gt; c");
+        assert_eq!(escape("Tom & Jerry"), "Tom &amp; Jerry");
+        assert_eq!(
+            escape(r#"say "hi" and 'bye'"#),
+            "say &quot;hi&quot; and &apos;bye&apos;"
+        );
+    }
+
+    #[test]
+    fn escape_entities_again() {
+        assert_eq!(escape("&amp;"), "&amp;amp;");
+        assert_eq!(escape("&lt;speak&gt;"), "&amp;lt;speak&amp;gt;");
+    }
+
+    #[test]
+    fn escape_keeps_unicode() {
+        assert_eq!(escape("你好，世界"), "你好，世界");
+        assert_eq!(escape("こんにちは 🎉👍"), "こんにちは 🎉👍");
+        assert_eq!(escape("🎉 & 你好"), "🎉 &amp; 你好");
+    }
+}
@@ -0,0 +1,137 @@
+//! Transcode synthesized audio with `ffmpeg`
+//!
+//! Enabled by the `ffmpeg` feature. An `ffmpeg` executable must be available in `PATH`.  
+//! Use [transcode] to convert [SynthesizedAudio] to bytes of another format,
+//! use [transcode_to_file] to write the converted audio to a file directly.
+
+use crate::{
+    error::{Error, Result},
+    tts::{client::SynthesizedAudio, parse_raw_audio_format, RawEncoding},
+};
+use std::{
+    io::{Read, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Target format of [transcode]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscodeFormat {
+    /// ADTS AAC
+    Aac,
+    /// AAC in MP4 container
+    M4a,
+    Flac,
+    Mp3,
+    /// Opus in Ogg container
+    Opus,
+    /// 16-bit PCM WAV
+    Wav,
+}
+
+impl TranscodeFormat {
+    /// Common file extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Aac => "aac",
+            Self::M4a => "m4a",
+            Self::Flac => "flac",
+            Self::Mp3 => "mp3",
+            Self::Opus => "opus",
+            Self::Wav => "wav",
+        }
+    }
+
+    fn output_args(&self) -> &'static [&'static str] {
+        match self {
+            Self::Aac => &["-c:a", "aac", "-f", "adts"],
+            // mp4 muxer needs fragmented output to write into a pipe
+            Self::M4a => &[
+                "-c:a",
+                "aac",
+                "-movflags",
+                "frag_keyframe+empty_moov",
+                "-f",
+                "mp4",
+            ],
+            Self::Flac => &["-c:a", "flac", "-f", "flac"],
+            Self::Mp3 => &["-c:a", "libmp3lame", "-f", "mp3"],
+            Self::Opus => &["-c:a", "libopus", "-f", "ogg"],
+            Self::Wav => &["-c:a", "pcm_s16le", "-f", "wav"],
+        }
+    }
+}
+
+/// Transcode [SynthesizedAudio] to another format and return the converted bytes
+pub fn transcode(audio: &SynthesizedAudio, format: TranscodeFormat) -> Result<Vec<u8>> {
+    run_ffmpeg(audio, format, "pipe:1")
+}
+
+/// Transcode [SynthesizedAudio] to another format and write it to `path`
+pub fn transcode_to_file(
+    audio: &SynthesizedAudio,
+    format: TranscodeFormat,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let path = path.as_ref().to_str().ok_or_else(|| {
+        Error::FfmpegError(format!("not a utf-8 path: {}", path.as_ref().display()))
+    })?;
+    run_ffmpeg(audio, format, path)?;
+    Ok(())
+}
+
+fn run_ffmpeg(audio: &SynthesizedAudio, format: TranscodeFormat, output: &str) -> Result<Vec<u8>> {
+    let mut command = Command::new("ffmpeg");
+    command.args(["-hide_banner", "-loglevel", "error", "-y"]);
+    // raw formats have no header, ffmpeg needs to be told how to read them
+    if let Some((encoding, sample_rate)) = parse_raw_audio_format(audio.audio_format.as_str()) {
+        let input_format = match encoding {
+            RawEncoding::Pcm16 => "s16le",
+            RawEncoding::ALaw => "alaw",
+            RawEncoding::MuLaw => "mulaw",
+        };
+        command.args([
+            "-f",
+            input_format,
+            "-ar",
+            &sample_rate.to_string(),
+            "-ac",
+            "1",
+        ]);
+    } else if audio.audio_format.as_str().starts_with("raw-") {
+        return Err(Error::FfmpegError(format!(
+            "not supported input format: {}",
+            audio.audio_format
+        )));
+    }
+    command
+        .args(["-i", "pipe:0"])
+        .args(format.output_args())
+        .arg(output)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let mut child = command.spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = child.stdout.take().unwrap();
+    // write input on the current thread while another thread drains output to avoid pipe deadlock
+    let reader = std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        stdout.read_to_end(&mut bytes).map(|_| bytes)
+    });
+    let written = stdin.write_all(&audio.audio_bytes);
+    drop(stdin);
+    let bytes = reader.join().expect("ffmpeg stdout reader panicked")?;
+
+    let mut stderr = String::new();
+    if let Some(mut err) = child.stderr.take() {
+        err.read_to_string(&mut stderr)?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(Error::FfmpegError(format!("{}: {}", status, stderr.trim())));
+    }
+    written?;
+    Ok(bytes)
+}
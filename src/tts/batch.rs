@@ -0,0 +1,119 @@
+//! Batch synthesis module
+//!
+//! [synthesize_parallel] synthesizes many [BatchItem]s over a bounded number of connections,
+//! each item with its own [SpeechConfig], e.g. the chapters of an audiobook or the lines of a dubbing script.
+
+use super::{
+    client::{connect_async, SynthesizedAudio},
+    SpeechConfig,
+};
+use crate::error::{Error, Result};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Text to synthesize with its [SpeechConfig]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchItem {
+    pub text: String,
+    pub config: SpeechConfig,
+}
+
+impl BatchItem {
+    pub fn new(text: impl Into<String>, config: SpeechConfig) -> Self {
+        Self {
+            text: text.into(),
+            config,
+        }
+    }
+}
+
+/// Synthesize `items` asynchronously, using at most `concurrency` connections.
+///
+/// Results are returned in the same order as `items`, a failed item does not stop the others.
+/// A connection which fails to synthesize is dropped and a new one is opened for the next item.
+/// If a connection can not be established, the error is reported for the item which needed it
+/// and the remaining items are cancelled with [Error::Cancelled].
+pub async fn synthesize_parallel(
+    items: &[BatchItem],
+    concurrency: usize,
+) -> Vec<Result<SynthesizedAudio>> {
+    synthesize_all(items.len(), concurrency, false, |index| {
+        (items[index].text.as_str(), &items[index].config)
+    })
+    .await
+}
+
+/// Synthesize `len` items given by `item` with at most `concurrency` workers, each with its own connection.
+///
+/// Remaining items are cancelled when a connection fails, and with `stop_on_fatal` also after an error
+/// which would repeat for every item, see [is_fatal].
+pub(super) async fn synthesize_all<'a>(
+    len: usize,
+    concurrency: usize,
+    stop_on_fatal: bool,
+    item: impl Fn(usize) -> (&'a str, &'a SpeechConfig) + Sync,
+) -> Vec<Result<SynthesizedAudio>> {
+    let next = AtomicUsize::new(0);
+    let cancelled = AtomicBool::new(false);
+
+    let worker = || async {
+        let mut results = Vec::new();
+        let mut client = None;
+        while !cancelled.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            if index >= len {
+                break;
+            }
+
+            if client.is_none() {
+                match connect_async().await {
+                    Ok(new_client) => client = Some(new_client),
+                    Err(err) => {
+                        cancelled.store(true, Ordering::Relaxed);
+                        results.push((index, Err(err)));
+                        break;
+                    }
+                }
+            }
+            let (text, config) = item(index);
+            let result = client.as_mut().unwrap().synthesize(text, config).await;
+            if let Err(ref err) = result {
+                if stop_on_fatal && is_fatal(err) {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+                client = None;
+            }
+            results.push((index, result));
+        }
+        results
+    };
+    let workers =
+        futures_util::future::join_all((0..concurrency.clamp(1, len.max(1))).map(|_| worker()))
+            .await;
+
+    let mut results = (0..len).map(|_| None).collect::<Vec<_>>();
+    for (index, result) in workers.into_iter().flatten() {
+        results[index] = Some(result);
+    }
+    results
+        .into_iter()
+        .map(|result| result.unwrap_or(Err(Error::Cancelled)))
+        .collect()
+}
+
+/// Errors not caused by the connection or the server load, e.g. an invalid [SpeechConfig]
+fn is_fatal(error: &Error) -> bool {
+    !error.is_transient()
+        && !matches!(
+            error,
+            Error::Timeout { .. } | Error::IncompleteSynthesis { .. }
+        )
+}
+
+// the batch futures must stay `Send`, so they can be spawned on multi-threaded runtimes
+#[allow(dead_code)]
+fn assert_send(items: &[BatchItem], texts: &[String], config: &SpeechConfig) {
+    fn send<T: Send>(_: T) {}
+    send(synthesize_parallel(items, 1));
+    send(super::client::synthesize_many(texts, config, 1));
+    send(super::client::synthesize_long_parallel("", config, 1));
+}
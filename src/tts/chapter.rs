@@ -0,0 +1,83 @@
+//! CUE sheet and chapter marker export
+//!
+//! Use [chapters] to split [AudioMetadata] into sentence chapters,
+//! and [cue_sheet] to render them as a CUE sheet for players and podcast tools.
+
+use super::AudioMetadata;
+
+/// Chapter
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+/// Split metadata into sentence chapters.
+///
+/// `SentenceBoundary` metadata is used if present,
+/// otherwise `WordBoundary` metadata is split at sentence punctuation.
+pub fn chapters(metadata: &[AudioMetadata]) -> Vec<Chapter> {
+    let mut chapters: Vec<Chapter> = metadata
+        .iter()
+        .filter(|x| x.metadata_type.as_deref() == Some("SentenceBoundary"))
+        .map(|x| Chapter {
+            title: x.text.clone().unwrap_or_default(),
+            start_ms: x.start().as_millis() as u64,
+            end_ms: x.end().as_millis() as u64,
+        })
+        .collect();
+    if !chapters.is_empty() {
+        return chapters;
+    }
+
+    let words: Vec<&AudioMetadata> = metadata
+        .iter()
+        .filter(|x| x.metadata_type.as_deref() == Some("WordBoundary"))
+        .collect();
+    let mut current: Option<Chapter> = None;
+    for (i, word) in words.iter().enumerate() {
+        let text = word.text.as_deref().unwrap_or_default();
+        let chapter = current.get_or_insert_with(|| Chapter {
+            title: String::new(),
+            start_ms: word.start().as_millis() as u64,
+            end_ms: 0,
+        });
+        // punctuation attaches to the previous word
+        if !chapter.title.is_empty() && text.chars().any(|c| c.is_alphanumeric()) {
+            chapter.title.push(' ');
+        }
+        chapter.title.push_str(text);
+        chapter.end_ms = word.end().as_millis() as u64;
+
+        if text.ends_with(['.', '!', '?', '。', '！', '？']) || i == words.len() - 1 {
+            chapters.extend(current.take());
+        }
+    }
+    chapters
+}
+
+/// Render chapters as a CUE sheet referencing `file_name`.
+///
+/// `file_type` is the CUE file type, e.g. `MP3`, `WAVE` or `BINARY`.
+pub fn cue_sheet(chapters: &[Chapter], file_name: &str, file_type: &str) -> String {
+    let mut cue = format!("FILE \"{}\" {}\n", escape_cue(file_name), file_type);
+    for (i, chapter) in chapters.iter().enumerate() {
+        // INDEX is mm:ss:ff, 75 frames per second
+        let frames = chapter.start_ms * 75 / 1000;
+        cue.push_str(&format!(
+            "  TRACK {:02} AUDIO\n    TITLE \"{}\"\n    INDEX 01 {:02}:{:02}:{:02}\n",
+            i + 1,
+            escape_cue(&chapter.title),
+            frames / 75 / 60,
+            frames / 75 % 60,
+            frames % 75
+        ));
+    }
+    cue
+}
+
+/// CUE strings can not contain double quotes
+fn escape_cue(text: &str) -> String {
+    text.replace('"', "'")
+}
@@ -1,134 +1,1751 @@
 //! TTS Client module
 
 use super::{
-    build_config_message, build_ssml_message, process_message,
+    batch::synthesize_all,
+    build_context_message, build_segments_ssml, build_ssml, build_ssml_message,
+    chapter::{chapters, cue_sheet, Chapter},
+    check_request,
+    connection::ConnectionConfig,
+    context::SpeechContext,
+    limit::RateLimit,
+    long::{split_text, stitch},
+    new_request_id, parse_raw_audio_format, process_message,
     proxy::{ProxyAsyncStream, ProxyStream},
+    speech_context,
+    statistics::{text_statistics, TextStatistics},
+    stream::SynthesizedResponse,
+    usage::UsageTracker,
     websocket_connect, websocket_connect_async, websocket_connect_proxy,
-    websocket_connect_proxy_async, AudioMetadata, ProcessedMessage, SpeechConfig, WebSocketStream,
-    WebSocketStreamAsync,
+    websocket_connect_proxy_async, websocket_connect_with, websocket_connect_with_async,
+    websocket_handshake, websocket_handshake_async, with_metadata_options, with_timeout,
+    AudioMetadata, AudioOutputFormat, MetadataOptions, ProcessedMessage, RawEncoding, SpeechConfig,
+    TextSegment, TurnState, WebSocketStream, WebSocketStreamAsync,
 };
-use crate::error::Result;
-use futures_util::{AsyncRead, AsyncWrite};
-use std::io::{Read, Write};
+use crate::{
+    error::{Error, Result},
+    retry::RetryPolicy,
+};
+use bytes::Bytes;
+use futures_util::{
+    AsyncRead, AsyncWrite, AsyncWriteExt, FutureExt, SinkExt, Stream, StreamExt, TryStreamExt,
+};
+use std::{
+    borrow::BorrowMut,
+    cell::Cell,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError, TryLockError, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+/// Attempts and backoff of reconnecting after the server closed the connection
+///
+/// Defaults to 3 attempts with backoff from 500ms, see [RetryPolicy].
+pub type ReconnectPolicy = RetryPolicy;
+
+/// time to wait for the Pong of a health check Ping
+const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// time to wait for the Close reply of the server
+const DEFAULT_CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Reconnect<T> = Box<dyn FnMut() -> Result<WebSocketStream<T>> + Send>;
+
+/// The TCP socket under a websocket, to set read timeouts
+type Socket<T> = fn(&WebSocketStream<T>) -> Option<&std::net::TcpStream>;
+
+type ReconnectAsync<T> = Box<
+    dyn FnMut() -> futures_util::future::BoxFuture<'static, Result<WebSocketStreamAsync<T>>> + Send,
+>;
+
+/// Websocket of a sync client with its activity, shared with the keep-alive thread
+struct Connection<T: Read + Write> {
+    /// `None` after the server closed the connection
+    websocket: Option<WebSocketStream<T>>,
+    /// last time a frame was sent
+    last_active: Instant,
+    keep_alive_interval: Option<Duration>,
+}
+
+impl<T: Read + Write> Connection<T> {
+    fn new(websocket: WebSocketStream<T>) -> Self {
+        Self {
+            websocket: Some(websocket),
+            last_active: Instant::now(),
+            keep_alive_interval: None,
+        }
+    }
+
+    /// Send a Ping if the connection was idle for the keep-alive interval, return the time until the next one is due
+    fn ping_if_idle(&mut self) -> Result<Option<Duration>> {
+        let Some(interval) = self.keep_alive_interval else {
+            return Ok(None);
+        };
+        let idle = self.last_active.elapsed();
+        if idle < interval {
+            return Ok(Some(interval - idle));
+        }
+        if let Some(ref mut websocket) = self.websocket {
+            if let Err(error) = websocket.send(tungstenite::Message::Ping(Vec::new())) {
+                self.websocket = None;
+                return Err(error.into());
+            }
+        }
+        self.last_active = Instant::now();
+        Ok(Some(interval))
+    }
+}
+
+/// Lock the shared connection, a panic of another holder does not make it unusable
+fn lock<C>(connection: &Mutex<C>) -> MutexGuard<'_, C> {
+    connection.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Ping the connection whenever it was idle for the keep-alive interval, until the client is dropped
+fn keep_alive_thread<T: Read + Write>(connection: Weak<Mutex<Connection<T>>>, interval: Duration) {
+    let mut wait = interval;
+    loop {
+        std::thread::sleep(wait);
+        let Some(connection) = connection.upgrade() else {
+            return;
+        };
+        let mut connection = match connection.try_lock() {
+            Ok(connection) => connection,
+            Err(TryLockError::Poisoned(error)) => error.into_inner(),
+            // a turn is running, the connection is active anyway
+            Err(TryLockError::WouldBlock) => continue,
+        };
+        // a failed Ping forgets the connection, the next synthesis reconnects
+        wait = match connection.ping_if_idle() {
+            Ok(Some(next)) => next,
+            _ => connection.keep_alive_interval.unwrap_or(interval),
+        };
+    }
+}
 
 /// Sync Client
-pub struct MSEdgeTTSClient<T: Read + Write>(WebSocketStream<T>);
+///
+/// One client runs any number of sequential syntheses over the same connection.
+/// speech.config is only sent again when it changes.
+/// Clients created by [connect] and [connect_proxy] reconnect transparently if the server closed the connection,
+/// retrying according to their [ReconnectPolicy].
+pub struct MSEdgeTTSClient<T: Read + Write> {
+    /// shared with the keep-alive thread
+    connection: Arc<Mutex<Connection<T>>>,
+    reconnect: Option<Reconnect<T>>,
+    reconnect_policy: ReconnectPolicy,
+    /// context of the last speech.config sent on this connection
+    sent_context: Option<SpeechContext>,
+    /// a turn was sent and its end not read yet
+    in_turn: bool,
+    /// `X-RequestId` of the last synthesis
+    request_id: Option<String>,
+    ping_timeout: Duration,
+    close_timeout: Duration,
+    rate_limit: Option<RateLimit>,
+    socket: Socket<T>,
+    read_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+}
 
 impl<T: Read + Write> MSEdgeTTSClient<T> {
-    /// Synthesize text to speech with a [SpeechConfig] synchronously
-    pub fn synthesize(&mut self, text: &str, config: &SpeechConfig) -> Result<SynthesizedAudio> {
-        let config_message = build_config_message(config);
-        let ssml_message = build_ssml_message(text, config);
-        self.0.send(config_message)?;
-        self.0.send(ssml_message)?;
-
-        let mut audio_bytes = Vec::new();
-        let mut audio_metadata = Vec::new();
-        let mut turn_start = false;
-        let mut response = false;
-        let mut turn_end = false;
+    fn new(
+        websocket: WebSocketStream<T>,
+        reconnect: Option<Reconnect<T>>,
+        socket: Socket<T>,
+    ) -> Self {
+        Self {
+            connection: Arc::new(Mutex::new(Connection::new(websocket))),
+            reconnect,
+            reconnect_policy: ReconnectPolicy::default(),
+            sent_context: None,
+            in_turn: false,
+            request_id: None,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            close_timeout: DEFAULT_CLOSE_TIMEOUT,
+            rate_limit: None,
+            socket,
+            read_timeout: None,
+            timeout: None,
+        }
+    }
+
+    /// Set the [ReconnectPolicy] used when the server closed the connection
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// `X-RequestId` of the last synthesis, also when it failed
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Delay requests according to `limit`, clones of a [RateLimit] share their budget
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Send a websocket Ping now if the connection was idle for the [keep_alive_interval](Self::keep_alive_interval).
+    ///
+    /// The keep-alive thread already does it periodically, this is only needed to ping at a specific time.
+    /// The Pong reply is consumed by the next synthesis.
+    pub fn keep_alive(&mut self) -> Result<()> {
+        if self.in_turn {
+            return Ok(());
+        }
+        lock(&self.connection).ping_if_idle().map(|_| ())
+    }
+
+    /// Time to wait for the Pong in [is_alive](Self::is_alive), default 5 seconds
+    pub fn ping_timeout(mut self, timeout: Duration) -> Self {
+        self.ping_timeout = timeout;
+        self
+    }
+
+    /// Check the connection with a websocket Ping, waiting at most the [ping_timeout](Self::ping_timeout) for the Pong.
+    ///
+    /// A dead connection is dropped, a client with reconnect opens a new one on the next synthesis.
+    /// Without access to the TCP socket, e.g. a custom stream, the wait is only checked between frames.
+    pub fn is_alive(&mut self) -> bool {
+        if self.in_turn {
+            // the rest of an abandoned turn would be read as the reply
+            lock(&self.connection).websocket = None;
+        }
+        let alive = self.try_ping().is_ok();
+        if !alive {
+            lock(&self.connection).websocket = None;
+        }
+        alive
+    }
+
+    fn try_ping(&mut self) -> Result<()> {
+        let mut connection = lock(&self.connection);
+        let connection = &mut *connection;
+        let websocket = connection
+            .websocket
+            .as_mut()
+            .ok_or(tungstenite::Error::AlreadyClosed)?;
+        let payload = uuid::Uuid::new_v4().as_bytes().to_vec();
+        websocket.send(tungstenite::Message::Ping(payload.clone()))?;
+        connection.last_active = Instant::now();
+        let deadline = connection.last_active + self.ping_timeout;
+        if let Some(socket) = (self.socket)(websocket) {
+            socket.set_read_timeout(Some(self.ping_timeout))?;
+        }
+        let result = loop {
+            match websocket.read() {
+                Ok(tungstenite::Message::Pong(pong)) if pong == payload => break Ok(()),
+                Ok(tungstenite::Message::Close(_)) => {
+                    break Err(tungstenite::Error::ConnectionClosed.into())
+                }
+                Ok(_) if Instant::now() < deadline => {}
+                Ok(_) => break Err(Error::Timeout { partial: None }),
+                Err(error) => break Err(error.into()),
+            }
+        };
+        if let Some(socket) = (self.socket)(websocket) {
+            socket.set_read_timeout(None)?;
+        }
+        result
+    }
+
+    /// Time to wait for the Close reply in [close](Self::close), default 5 seconds
+    pub fn close_timeout(mut self, timeout: Duration) -> Self {
+        self.close_timeout = timeout;
+        self
+    }
+
+    /// Close the connection with a websocket Close frame and wait for the Close reply of the server.
+    ///
+    /// Fails with [Error::Timeout] if no reply arrives within the [close_timeout](Self::close_timeout),
+    /// the connection is dropped either way.
+    /// Without access to the TCP socket, e.g. a custom stream, the wait is only checked between frames.
+    /// A client with reconnect opens a new connection on the next synthesis.
+    /// Dropping the client also sends a Close frame, without waiting for the reply.
+    pub fn close(&mut self) -> Result<()> {
+        let Some(mut websocket) = lock(&self.connection).websocket.take() else {
+            return Ok(());
+        };
+        self.sent_context = None;
+        self.in_turn = false;
+        websocket.close(None)?;
+        let deadline = Instant::now() + self.close_timeout;
+        if let Some(socket) = (self.socket)(&websocket) {
+            socket.set_read_timeout(Some(self.close_timeout))?;
+        }
         loop {
-            if turn_end {
-                break;
+            match websocket.read() {
+                Ok(_) if Instant::now() < deadline => {}
+                Ok(_) => return Err(Error::Timeout { partial: None }),
+                Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+                Err(tungstenite::Error::Io(error))
+                    if matches!(
+                        error.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Err(Error::Timeout { partial: None })
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    /// Fail with [Error::Timeout] if the server sends no frame within `timeout` during a synthesis
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// Fail with [Error::Timeout] if a synthesis does not finish within `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Synthesize text to speech with a [SpeechConfig] synchronously.
+    ///
+    /// `text` is plain text, XML special characters are escaped. Use [synthesize_ssml](Self::synthesize_ssml) to send markup.
+    /// A voice name with XML special characters fails with [Error::InvalidSpeechConfig],
+    /// build `config` with [SpeechConfigBuilder](super::SpeechConfigBuilder) to check the other fields.
+    ///
+    /// If an error occurs after some audio was received,
+    /// [Error::IncompleteSynthesis] carries the partial audio and the error.
+    /// A [timed out](Self::timeout) synthesis fails with [Error::Timeout] carrying the partial audio.
+    pub fn synthesize(&mut self, text: &str, config: &SpeechConfig) -> Result<SynthesizedAudio> {
+        let mut collector = AudioCollector::default();
+        let result = self.turn(text, &build_ssml(text, config), config, |message| {
+            collector.push(message);
+            Ok(())
+        });
+        collector.finish(text, config, self.request_id.clone(), result)
+    }
+
+    /// Synthesize text like [synthesize](Self::synthesize), requesting `metadata_options` instead of those of `config`.
+    ///
+    /// `speech.config` is only re-sent when the options differ from the previous request on this connection,
+    /// so caption and audio-only requests can share one connection.
+    pub fn synthesize_with_metadata(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        metadata_options: Option<MetadataOptions>,
+    ) -> Result<SynthesizedAudio> {
+        match metadata_options {
+            Some(metadata_options) => {
+                self.synthesize(text, &with_metadata_options(config, metadata_options))
             }
+            None => self.synthesize(text, config),
+        }
+    }
+
+    /// Synthesize text with a [SpeechConfig] synchronously, passing each audio chunk and metadata to `callback` as soon as it arrives.
+    ///
+    /// Playback or upload can start with the first chunk, without the [split stream](super::stream) API.
+    pub fn synthesize_with_callback(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        mut callback: impl FnMut(SynthesizedResponse),
+    ) -> Result<()> {
+        self.turn(text, &build_ssml(text, config), config, |message| {
+            callback(message.into());
+            Ok(())
+        })
+    }
 
-            let message = self.0.read()?;
-            let message = process_message(message, &mut turn_start, &mut response, &mut turn_end)?;
-            if let Some(message) = message {
-                match message {
-                    ProcessedMessage::AudioBytes(payload) => {
-                        audio_bytes.push(payload);
+    /// Synthesize text of any length with a [SpeechConfig] synchronously.
+    ///
+    /// The text is [split](super::long::split_text) on sentence boundaries into chunks which fit in one request,
+    /// the chunks are synthesized one after another and [stitched](super::long::stitch) into one [SynthesizedAudio].
+    pub fn synthesize_long(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        let mut chunks = Vec::new();
+        for chunk in split_text(text, config) {
+            chunks.push(self.synthesize(&chunk, config)?);
+        }
+        Ok(stitch(config.audio_format.clone(), chunks))
+    }
+
+    /// Synthesize text segments with pauses between them synchronously.
+    ///
+    /// [text](SynthesizedAudio::text) of the result is the concatenated text of all segments.
+    pub fn synthesize_segments(
+        &mut self,
+        segments: &[TextSegment],
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        let (ssml, text) = build_segments_ssml(segments, config);
+        let mut collector = AudioCollector::default();
+        let result = self.turn(&text, &ssml, config, |message| {
+            collector.push(message);
+            Ok(())
+        });
+        collector.finish(&text, config, self.request_id.clone(), result)
+    }
+
+    /// Synthesize a complete SSML document synchronously, e.g. one built with [Speak](crate::ssml::Speak).
+    ///
+    /// `ssml` is sent as it is, escape untrusted text with [escape](crate::ssml::escape).
+    /// The document may contain multiple `<voice>` elements, audio and metadata of all voices
+    /// are collected in order. Only [audio_format](SpeechConfig::audio_format) of `config` is used.
+    pub fn synthesize_ssml(
+        &mut self,
+        ssml: &str,
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        let mut collector = AudioCollector::default();
+        let result = self.turn(ssml, ssml, config, |message| {
+            collector.push(message);
+            Ok(())
+        });
+        collector.finish(ssml, config, self.request_id.clone(), result)
+    }
+
+    /// Synthesize text to speech and pipe audio bytes into the stdin of `command` as they arrive.
+    ///
+    /// `command` is a player reading audio from stdin, e.g. `mpv -`, `ffplay -nodisp -autoexit -` or `aplay`.
+    /// If the player exits early, the rest of the audio is discarded.
+    /// Return the exit status of the player.
+    pub fn synthesize_to_command(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        command: &mut Command,
+    ) -> Result<ExitStatus> {
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        let mut stdin = child.stdin.take();
+        let result = self.turn(text, &build_ssml(text, config), config, |message| {
+            if let ProcessedMessage::AudioBytes((bytes, index)) = message {
+                if let Some(ref mut writer) = stdin {
+                    // player exited, keep reading to finish the turn
+                    if writer.write_all(&bytes[index..]).is_err() {
+                        stdin = None;
                     }
-                    ProcessedMessage::AudioMetadata(metadata) => {
-                        audio_metadata.extend(metadata);
+                }
+            }
+            Ok(())
+        });
+        drop(stdin);
+        let status = child.wait()?;
+        result?;
+        Ok(status)
+    }
+
+    /// Synthesize text to speech and write audio bytes into a named pipe as they arrive.
+    ///
+    /// See [open_pipe](super::pipe::open_pipe) for how `path` and `create` are handled.
+    /// If the reader closes the pipe, the rest of the audio is discarded and the write error is returned.
+    pub fn synthesize_to_pipe(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        path: impl AsRef<Path>,
+        create: bool,
+    ) -> Result<()> {
+        let mut pipe = super::pipe::open_pipe(path, create)?;
+        self.synthesize_to_writer(text, config, &mut pipe)
+    }
+
+    /// Synthesize text to speech and write audio bytes into `writer` as they arrive, without buffering the whole audio.
+    ///
+    /// If writing fails, the rest of the audio is discarded and the write error is returned.
+    pub fn synthesize_to_writer(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        let mut write_result = Ok(());
+        self.turn(text, &build_ssml(text, config), config, |message| {
+            if let ProcessedMessage::AudioBytes((bytes, index)) = message {
+                // writer failed, keep reading to finish the turn
+                if write_result.is_ok() {
+                    write_result = writer.write_all(&bytes[index..]);
+                }
+            }
+            Ok(())
+        })?;
+        write_result?;
+        Ok(writer.flush()?)
+    }
+
+    /// Synthesize text with a [SpeechConfig] synchronously, reading audio bytes lazily as frames arrive.
+    ///
+    /// The returned [AudioReader] can be fed into any decoder expecting a reader, e.g. rodio `Decoder`.
+    /// Dropping it before the end of the audio abandons the turn, the next synthesis reconnects,
+    /// or fails with [Error::TurnAbandoned] for a client without reconnect, e.g. [from_stream](Self::from_stream).
+    pub fn synthesize_reader(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+    ) -> Result<AudioReader<'_, T>> {
+        let ssml = build_ssml(text, config);
+        self.request_id = Some(new_request_id(config));
+        check_request(&ssml, config)?;
+        if let Some(ref limit) = self.rate_limit {
+            limit.wait();
+        }
+        self.ensure_connected()?;
+        if let Err(error) = self.send_request(text, &ssml, speech_context(config)) {
+            if self.reconnect.is_none() || !is_connection_lost(&error) {
+                return Err(error);
+            }
+            lock(&self.connection).websocket = None;
+            self.ensure_connected()?;
+            self.send_request(text, &ssml, speech_context(config))?;
+        }
+        Ok(AudioReader {
+            client: self,
+            state: TurnState::default(),
+            buffer: Vec::new(),
+            position: 0,
+        })
+    }
+
+    /// Send a synthesis request and pass each processed message to `on_message` until the turn ends.
+    ///
+    /// If the connection was lost before any message arrived, reconnect and send the request once more.
+    fn turn(
+        &mut self,
+        text: &str,
+        ssml: &str,
+        config: &SpeechConfig,
+        mut on_message: impl FnMut(ProcessedMessage) -> Result<()>,
+    ) -> Result<()> {
+        self.request_id = Some(new_request_id(config));
+        check_request(ssml, config)?;
+        if let Some(ref limit) = self.rate_limit {
+            limit.wait();
+        }
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let delivered = Cell::new(false);
+        let mut on_message = |message| {
+            delivered.set(true);
+            on_message(message)
+        };
+        match self.try_turn(text, ssml, config, deadline, &mut on_message) {
+            Err(error)
+                if !delivered.get() && self.reconnect.is_some() && is_connection_lost(&error) =>
+            {
+                lock(&self.connection).websocket = None;
+                self.try_turn(text, ssml, config, deadline, &mut on_message)
+            }
+            result => result,
+        }
+    }
+
+    fn try_turn(
+        &mut self,
+        text: &str,
+        ssml: &str,
+        config: &SpeechConfig,
+        deadline: Option<Instant>,
+        on_message: &mut impl FnMut(ProcessedMessage) -> Result<()>,
+    ) -> Result<()> {
+        self.ensure_connected()?;
+        let result = self.send_and_read(text, ssml, config, deadline, on_message);
+        if result.is_err() && self.reconnect.is_some() {
+            // the connection may be in the middle of a turn, start over next time
+            lock(&self.connection).websocket = None;
+        }
+        result
+    }
+
+    fn send_and_read(
+        &mut self,
+        text: &str,
+        ssml: &str,
+        config: &SpeechConfig,
+        deadline: Option<Instant>,
+        on_message: &mut impl FnMut(ProcessedMessage) -> Result<()>,
+    ) -> Result<()> {
+        self.send_request(text, ssml, speech_context(config))?;
+        let mut connection = lock(&self.connection);
+        let websocket = connection
+            .websocket
+            .as_mut()
+            .ok_or(tungstenite::Error::AlreadyClosed)?;
+
+        let timed = self.read_timeout.is_some() || deadline.is_some();
+        let mut state = TurnState::default();
+        while !state.turn_end {
+            let read_timeout = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(Error::Timeout { partial: None });
                     }
+                    Some(self.read_timeout.map_or(remaining, |t| t.min(remaining)))
+                }
+                None => self.read_timeout,
+            };
+            // without a socket, e.g. a custom stream, deadlines are only checked between frames
+            if let Some(socket) = (self.socket)(websocket).filter(|_| timed) {
+                socket.set_read_timeout(read_timeout)?;
+            }
+            let message = match websocket.read() {
+                Ok(message) => message,
+                Err(tungstenite::Error::Io(error))
+                    if timed
+                        && matches!(
+                            error.kind(),
+                            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                        ) =>
+                {
+                    return Err(Error::Timeout { partial: None })
                 }
+                Err(error) => return Err(error.into()),
+            };
+            if let Some(message) = process_message(message, &mut state)? {
+                on_message(message)?;
             }
         }
+        if let Some(socket) = (self.socket)(websocket).filter(|_| timed) {
+            socket.set_read_timeout(None)?;
+        }
+        drop(connection);
+        self.end_turn(&state)
+    }
 
-        let audio_bytes = audio_bytes
-            .iter()
-            .flat_map(|(bytes, index)| &bytes[*index..])
-            .copied()
-            .collect();
+    /// Send speech.config if it changed and the SSML of a new turn
+    fn send_request(&mut self, text: &str, ssml: &str, context: SpeechContext) -> Result<()> {
+        let mut connection = lock(&self.connection);
+        let connection = &mut *connection;
+        let websocket = connection
+            .websocket
+            .as_mut()
+            .ok_or(tungstenite::Error::AlreadyClosed)?;
+        if self.sent_context.as_ref() != Some(&context) {
+            websocket.send(build_context_message(&context))?;
+            self.sent_context = Some(context);
+        }
+        websocket.send(build_ssml_message(
+            ssml,
+            self.request_id.as_deref().unwrap_or_default(),
+        ))?;
+        self.in_turn = true;
+        connection.last_active = Instant::now();
+        UsageTracker::global().add_characters(text);
+        Ok(())
+    }
 
-        Ok(SynthesizedAudio {
-            audio_format: config.audio_format.clone(),
-            audio_bytes,
-            audio_metadata,
-        })
+    /// Mark the turn finished, forget the connection if the server closed it
+    fn end_turn(&mut self, state: &TurnState) -> Result<()> {
+        self.in_turn = false;
+        if state.closed {
+            lock(&self.connection).websocket = None;
+            self.sent_context = None;
+            if !state.is_complete() {
+                return Err(tungstenite::Error::ConnectionClosed.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Reconnect if the server closed the connection or a previous turn was abandoned
+    fn ensure_connected(&mut self) -> Result<()> {
+        let mut connection = lock(&self.connection);
+        if self.in_turn {
+            // frames of the abandoned turn would be read as the next one
+            connection.websocket = None;
+            self.in_turn = false;
+            if self.reconnect.is_none() {
+                return Err(Error::TurnAbandoned);
+            }
+        }
+        if connection.websocket.is_none() {
+            let reconnect = self
+                .reconnect
+                .as_mut()
+                .ok_or(tungstenite::Error::AlreadyClosed)?;
+            connection.websocket = Some(self.reconnect_policy.retry(reconnect)?);
+            connection.last_active = Instant::now();
+            self.sent_context = None;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Read + Write + Send + 'static> MSEdgeTTSClient<T> {
+    /// Send a websocket Ping from a background thread whenever the connection was idle for `interval`,
+    /// so the server does not drop an idle connection between syntheses.
+    ///
+    /// The thread ends when the client is dropped. The Pong replies are consumed by the next synthesis.
+    pub fn keep_alive_interval(self, interval: Duration) -> Self {
+        let started = lock(&self.connection)
+            .keep_alive_interval
+            .replace(interval)
+            .is_some();
+        if !started {
+            let connection = Arc::downgrade(&self.connection);
+            std::thread::spawn(move || keep_alive_thread(connection, interval));
+        }
+        self
+    }
+}
+
+impl<T: Read + Write> Drop for MSEdgeTTSClient<T> {
+    fn drop(&mut self) {
+        if let Some(ref mut websocket) = lock(&self.connection).websocket {
+            // best effort, the server may already be gone
+            let _ = websocket.close(None);
+            let _ = websocket.flush();
+        }
+    }
+}
+
+/// Websocket of an async client with its activity, shared with the keep-alive task
+struct ConnectionAsync<T: AsyncRead + AsyncWrite + Unpin> {
+    /// `None` after the server closed the connection
+    websocket: Option<WebSocketStreamAsync<T>>,
+    /// last time a frame was sent
+    last_active: Instant,
+    keep_alive_interval: Option<Duration>,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> ConnectionAsync<T> {
+    fn new(websocket: WebSocketStreamAsync<T>) -> Self {
+        Self {
+            websocket: Some(websocket),
+            last_active: Instant::now(),
+            keep_alive_interval: None,
+        }
+    }
+
+    /// Send a Ping if the connection was idle for the keep-alive interval, return the time until the next one is due
+    async fn ping_if_idle(&mut self) -> Result<Option<Duration>> {
+        let Some(interval) = self.keep_alive_interval else {
+            return Ok(None);
+        };
+        let idle = self.last_active.elapsed();
+        if idle < interval {
+            return Ok(Some(interval - idle));
+        }
+        if let Some(ref mut websocket) = self.websocket {
+            if let Err(error) = websocket.send(tungstenite::Message::Ping(Vec::new())).await {
+                self.websocket = None;
+                return Err(error.into());
+            }
+        }
+        self.last_active = Instant::now();
+        Ok(Some(interval))
+    }
+}
+
+/// Ping the connection whenever it was idle for the keep-alive interval, until the client is dropped
+async fn keep_alive_task<T: AsyncRead + AsyncWrite + Unpin>(
+    connection: Weak<async_lock::Mutex<ConnectionAsync<T>>>,
+    interval: Duration,
+) {
+    let mut wait = interval;
+    loop {
+        async_io::Timer::after(wait).await;
+        let Some(connection) = connection.upgrade() else {
+            return;
+        };
+        // a turn is running, the connection is active anyway
+        let Some(mut connection) = connection.try_lock() else {
+            continue;
+        };
+        // a failed Ping forgets the connection, the next synthesis reconnects
+        wait = match connection.ping_if_idle().await {
+            Ok(Some(next)) => next,
+            _ => connection.keep_alive_interval.unwrap_or(interval),
+        };
     }
 }
 
 /// Async Client
-pub struct MSEdgeTTSClientAsync<T>(WebSocketStreamAsync<T>);
+///
+/// Runs sequential syntheses over one connection like [MSEdgeTTSClient].
+pub struct MSEdgeTTSClientAsync<T: AsyncRead + AsyncWrite + Unpin> {
+    /// shared with the keep-alive task
+    connection: Arc<async_lock::Mutex<ConnectionAsync<T>>>,
+    reconnect: Option<ReconnectAsync<T>>,
+    reconnect_policy: ReconnectPolicy,
+    /// context of the last speech.config sent on this connection
+    sent_context: Option<SpeechContext>,
+    /// a turn was sent and its end not read yet
+    in_turn: bool,
+    /// `X-RequestId` of the last synthesis
+    request_id: Option<String>,
+    ping_timeout: Duration,
+    close_timeout: Duration,
+    rate_limit: Option<RateLimit>,
+}
 
 impl<T: AsyncRead + AsyncWrite + Unpin> MSEdgeTTSClientAsync<T> {
-    /// Synthesize text to speech with a [SpeechConfig] asynchronously
+    fn new(websocket: WebSocketStreamAsync<T>, reconnect: Option<ReconnectAsync<T>>) -> Self {
+        Self {
+            connection: Arc::new(async_lock::Mutex::new(ConnectionAsync::new(websocket))),
+            reconnect,
+            reconnect_policy: ReconnectPolicy::default(),
+            sent_context: None,
+            in_turn: false,
+            request_id: None,
+            ping_timeout: DEFAULT_PING_TIMEOUT,
+            close_timeout: DEFAULT_CLOSE_TIMEOUT,
+            rate_limit: None,
+        }
+    }
+
+    /// Set the [ReconnectPolicy] used when the server closed the connection
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// `X-RequestId` of the last synthesis, also when it failed
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Delay requests according to `limit`, clones of a [RateLimit] share their budget
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// Send a websocket Ping now if the connection was idle for the [keep_alive_interval](Self::keep_alive_interval),
+    /// see [MSEdgeTTSClient::keep_alive].
+    pub async fn keep_alive(&mut self) -> Result<()> {
+        if self.in_turn {
+            return Ok(());
+        }
+        self.connection
+            .lock()
+            .await
+            .ping_if_idle()
+            .await
+            .map(|_| ())
+    }
+
+    /// Time to wait for the Pong in [ping](Self::ping), default 5 seconds
+    pub fn ping_timeout(mut self, timeout: Duration) -> Self {
+        self.ping_timeout = timeout;
+        self
+    }
+
+    /// Check the connection with a websocket Ping, waiting at most the [ping_timeout](Self::ping_timeout) for the Pong.
+    ///
+    /// Fails with [Error::Timeout] if no Pong arrives in time.
+    /// A dead connection is dropped, a client with reconnect opens a new one on the next synthesis.
+    pub async fn ping(&mut self) -> Result<()> {
+        if self.in_turn {
+            // the rest of an abandoned turn would be read as the reply
+            self.connection.lock().await.websocket = None;
+        }
+        let result = self.try_ping().await;
+        if result.is_err() {
+            self.connection.lock().await.websocket = None;
+        }
+        result
+    }
+
+    async fn try_ping(&mut self) -> Result<()> {
+        let mut connection = self.connection.lock().await;
+        let connection = &mut *connection;
+        let websocket = connection
+            .websocket
+            .as_mut()
+            .ok_or(tungstenite::Error::AlreadyClosed)?;
+        let payload = uuid::Uuid::new_v4().as_bytes().to_vec();
+        websocket
+            .send(tungstenite::Message::Ping(payload.clone()))
+            .await?;
+        connection.last_active = Instant::now();
+        let pong = async {
+            while let Some(message) = websocket.next().await {
+                match message? {
+                    tungstenite::Message::Pong(pong) if pong == payload => return Ok(()),
+                    tungstenite::Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            Err(tungstenite::Error::ConnectionClosed.into())
+        };
+        with_timeout(Some(self.ping_timeout), pong)
+            .await
+            .map_err(|_| Error::Timeout { partial: None })?
+    }
+
+    /// Time to wait for the Close reply in [close](Self::close), default 5 seconds
+    pub fn close_timeout(mut self, timeout: Duration) -> Self {
+        self.close_timeout = timeout;
+        self
+    }
+
+    /// Close the connection with a websocket Close frame and wait for the Close reply of the server.
+    ///
+    /// Fails with [Error::Timeout] if no reply arrives within the [close_timeout](Self::close_timeout),
+    /// the connection is dropped either way.
+    /// A client with reconnect opens a new connection on the next synthesis.
+    /// Dropping the client also sends a Close frame if the socket accepts it without waiting.
+    pub async fn close(&mut self) -> Result<()> {
+        let Some(mut websocket) = self.connection.lock().await.websocket.take() else {
+            return Ok(());
+        };
+        self.sent_context = None;
+        self.in_turn = false;
+        websocket.close(None).await?;
+        let reply = async {
+            while let Some(message) = websocket.next().await {
+                message?;
+            }
+            Ok(())
+        };
+        async_std::future::timeout(self.close_timeout, reply)
+            .await
+            .map_err(|_| Error::Timeout { partial: None })?
+    }
+
+    /// Synthesize text to speech with a [SpeechConfig] asynchronously.
+    ///
+    /// `text` is plain text, XML special characters are escaped. Use [synthesize_ssml](Self::synthesize_ssml) to send markup.
+    /// A voice name with XML special characters fails with [Error::InvalidSpeechConfig],
+    /// build `config` with [SpeechConfigBuilder](super::SpeechConfigBuilder) to check the other fields.
+    ///
+    /// If an error occurs after some audio was received,
+    /// [Error::IncompleteSynthesis] carries the partial audio and the error.
     pub async fn synthesize(
         &mut self,
         text: &str,
         config: &SpeechConfig,
     ) -> Result<SynthesizedAudio> {
-        use futures_util::{SinkExt, StreamExt};
-
-        let config_message = build_config_message(config);
-        let ssml_message = build_ssml_message(text, config);
-        self.0.send(config_message).await?;
-        self.0.send(ssml_message).await?;
-
-        let mut audio_bytes = Vec::new();
-        let mut audio_metadata = Vec::new();
-        let mut turn_start = false;
-        let mut response = false;
-        let mut turn_end = false;
-        loop {
-            if turn_end {
-                break;
-            }
-
-            if let Some(message) = self.0.next().await {
-                let message = message?;
-                let response =
-                    process_message(message, &mut turn_start, &mut response, &mut turn_end)?;
-                if let Some(response) = response {
-                    match response {
-                        ProcessedMessage::AudioBytes(payload) => {
-                            audio_bytes.push(payload);
-                        }
-                        ProcessedMessage::AudioMetadata(metadata) => {
-                            audio_metadata.extend(metadata);
-                        }
-                    }
+        let mut collector = AudioCollector::default();
+        let result = self
+            .turn(text, &build_ssml(text, config), config, |message| {
+                collector.push(message);
+                Ok(())
+            })
+            .await;
+        collector.finish(text, config, self.request_id.clone(), result)
+    }
+
+    /// Synthesize text like [synthesize](Self::synthesize), requesting `metadata_options` instead of those of `config`.
+    ///
+    /// `speech.config` is only re-sent when the options differ from the previous request on this connection,
+    /// so caption and audio-only requests can share one connection.
+    pub async fn synthesize_with_metadata(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        metadata_options: Option<MetadataOptions>,
+    ) -> Result<SynthesizedAudio> {
+        match metadata_options {
+            Some(metadata_options) => {
+                self.synthesize(text, &with_metadata_options(config, metadata_options))
+                    .await
+            }
+            None => self.synthesize(text, config).await,
+        }
+    }
+
+    /// Synthesize text with a [SpeechConfig] asynchronously, passing each audio chunk and metadata to `callback` as soon as it arrives.
+    ///
+    /// Playback or upload can start with the first chunk, without the [split stream](super::stream) API.
+    pub async fn synthesize_with_callback(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        mut callback: impl FnMut(SynthesizedResponse),
+    ) -> Result<()> {
+        self.turn(text, &build_ssml(text, config), config, |message| {
+            callback(message.into());
+            Ok(())
+        })
+        .await
+    }
+
+    /// Synthesize text of any length with a [SpeechConfig] asynchronously.
+    ///
+    /// See [MSEdgeTTSClient::synthesize_long], [synthesize_long_parallel] synthesizes the chunks concurrently.
+    pub async fn synthesize_long(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        let mut chunks = Vec::new();
+        for chunk in split_text(text, config) {
+            chunks.push(self.synthesize(&chunk, config).await?);
+        }
+        Ok(stitch(config.audio_format.clone(), chunks))
+    }
+
+    /// Synthesize text segments with pauses between them asynchronously.
+    ///
+    /// [text](SynthesizedAudio::text) of the result is the concatenated text of all segments.
+    pub async fn synthesize_segments(
+        &mut self,
+        segments: &[TextSegment],
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        let (ssml, text) = build_segments_ssml(segments, config);
+        let mut collector = AudioCollector::default();
+        let result = self
+            .turn(&text, &ssml, config, |message| {
+                collector.push(message);
+                Ok(())
+            })
+            .await;
+        collector.finish(&text, config, self.request_id.clone(), result)
+    }
+
+    /// Synthesize a complete SSML document asynchronously, e.g. one built with [Speak](crate::ssml::Speak).
+    ///
+    /// `ssml` is sent as it is, escape untrusted text with [escape](crate::ssml::escape).
+    /// The document may contain multiple `<voice>` elements, audio and metadata of all voices
+    /// are collected in order. Only [audio_format](SpeechConfig::audio_format) of `config` is used.
+    pub async fn synthesize_ssml(
+        &mut self,
+        ssml: &str,
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        let mut collector = AudioCollector::default();
+        let result = self
+            .turn(ssml, ssml, config, |message| {
+                collector.push(message);
+                Ok(())
+            })
+            .await;
+        collector.finish(ssml, config, self.request_id.clone(), result)
+    }
+
+    /// Synthesize text to speech and write audio bytes into `writer` as they arrive, without buffering the whole audio.
+    ///
+    /// If writing fails, the rest of the audio is discarded and the write error is returned.
+    pub async fn synthesize_to_writer(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<()> {
+        let mut responses = std::pin::pin!(turn_stream(&mut *self, text, config));
+        let mut write_result = Ok(());
+        while let Some(response) = responses.next().await {
+            if let SynthesizedResponse::AudioBytes(bytes) = response? {
+                // writer failed, keep reading to finish the turn
+                if write_result.is_ok() {
+                    write_result = writer.write_all(&bytes).await;
                 }
             }
         }
+        write_result?;
+        Ok(writer.flush().await?)
+    }
 
-        let audio_bytes = audio_bytes
-            .iter()
-            .flat_map(|(bytes, index)| &bytes[*index..])
-            .copied()
-            .collect();
+    /// Synthesize text to speech with a [SpeechConfig] and stream audio chunks and metadata as they arrive.
+    ///
+    /// Dropping the stream before its end abandons the turn, the next synthesis reconnects,
+    /// or fails with [Error::TurnAbandoned] for a client without reconnect, e.g. [from_stream](Self::from_stream).
+    pub fn synthesize_stream(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+    ) -> impl Stream<Item = Result<SynthesizedResponse>> + '_ {
+        turn_stream(self, text, config)
+    }
+
+    /// Synthesize text to speech with a [SpeechConfig] and stream audio bytes as they arrive.
+    ///
+    /// The returned stream owns the client, so it can be used as a HTTP response body directly,
+    /// e.g. axum `Body::from_stream` or hyper `StreamBody`.
+    pub fn into_audio_stream(
+        self,
+        text: &str,
+        config: &SpeechConfig,
+    ) -> impl Stream<Item = Result<Bytes>> {
+        turn_stream(self, text, config)
+            .try_filter_map(audio_bytes)
+            .map_ok(Bytes::from)
+    }
+
+    /// Synthesize text to speech with a [SpeechConfig] and read audio bytes as they arrive with [AsyncRead].
+    ///
+    /// The reader owns the client, e.g. to copy into an async file or a HTTP response body.
+    pub fn into_audio_reader(self, text: &str, config: &SpeechConfig) -> impl AsyncRead + Unpin {
+        audio_reader(turn_stream(self, text, config))
+    }
+
+    /// Synthesize text to speech with a [SpeechConfig] and read audio bytes as they arrive with [AsyncRead].
+    ///
+    /// Dropping the reader before the end of the audio abandons the turn, the next synthesis reconnects,
+    /// or fails with [Error::TurnAbandoned] for a client without reconnect, e.g. [from_stream](Self::from_stream).
+    pub fn synthesize_async_reader(
+        &mut self,
+        text: &str,
+        config: &SpeechConfig,
+    ) -> impl AsyncRead + Unpin + '_ {
+        audio_reader(turn_stream(self, text, config))
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> MSEdgeTTSClientAsync<T> {
+    /// Send a websocket Ping from a background task whenever the connection was idle for `interval`,
+    /// see [MSEdgeTTSClient::keep_alive_interval].
+    ///
+    /// The task runs on the async-std executor and ends when the client is dropped.
+    pub fn keep_alive_interval(self, interval: Duration) -> Self {
+        let started = match self.connection.try_lock() {
+            Some(mut connection) => connection.keep_alive_interval.replace(interval).is_some(),
+            // only the keep-alive task can hold the lock here, so it is running
+            None => true,
+        };
+        if !started {
+            async_std::task::spawn(keep_alive_task(Arc::downgrade(&self.connection), interval));
+        }
+        self
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Drop for MSEdgeTTSClientAsync<T> {
+    fn drop(&mut self) {
+        let Some(mut connection) = self.connection.try_lock() else {
+            return;
+        };
+        if let Some(ref mut websocket) = connection.websocket {
+            // drop can not await, send the Close frame only if it does not block
+            let _ = websocket.close(None).now_or_never();
+        }
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> MSEdgeTTSClientAsync<T> {
+    /// Send a synthesis request and pass each processed message to `on_message` until the turn ends.
+    ///
+    /// If the connection was lost before any message arrived, reconnect and send the request once more.
+    async fn turn(
+        &mut self,
+        text: &str,
+        ssml: &str,
+        config: &SpeechConfig,
+        mut on_message: impl FnMut(ProcessedMessage) -> Result<()>,
+    ) -> Result<()> {
+        self.request_id = Some(new_request_id(config));
+        check_request(ssml, config)?;
+        if let Some(ref limit) = self.rate_limit {
+            limit.wait_async().await;
+        }
+        // atomic so the async turn stays `Send`
+        let delivered = AtomicBool::new(false);
+        let mut on_message = |message| {
+            delivered.store(true, Ordering::Relaxed);
+            on_message(message)
+        };
+        match self.try_turn(text, ssml, config, &mut on_message).await {
+            Err(error)
+                if !delivered.load(Ordering::Relaxed)
+                    && self.reconnect.is_some()
+                    && is_connection_lost(&error) =>
+            {
+                self.connection.lock().await.websocket = None;
+                self.try_turn(text, ssml, config, &mut on_message).await
+            }
+            result => result,
+        }
+    }
+
+    async fn try_turn(
+        &mut self,
+        text: &str,
+        ssml: &str,
+        config: &SpeechConfig,
+        on_message: &mut impl FnMut(ProcessedMessage) -> Result<()>,
+    ) -> Result<()> {
+        self.ensure_connected().await?;
+        let result = self.send_and_read(text, ssml, config, on_message).await;
+        if result.is_err() && self.reconnect.is_some() {
+            // the connection may be in the middle of a turn, start over next time
+            self.connection.lock().await.websocket = None;
+        }
+        result
+    }
+
+    async fn send_and_read(
+        &mut self,
+        text: &str,
+        ssml: &str,
+        config: &SpeechConfig,
+        on_message: &mut impl FnMut(ProcessedMessage) -> Result<()>,
+    ) -> Result<()> {
+        self.send_request(text, ssml, speech_context(config))
+            .await?;
+        let mut connection = self.connection.lock().await;
+        let websocket = connection
+            .websocket
+            .as_mut()
+            .ok_or(tungstenite::Error::AlreadyClosed)?;
+
+        let mut state = TurnState::default();
+        while !state.turn_end {
+            let message = match websocket.next().await {
+                Some(message) => message?,
+                None => return Err(tungstenite::Error::ConnectionClosed.into()),
+            };
+            if let Some(message) = process_message(message, &mut state)? {
+                on_message(message)?;
+            }
+        }
+        drop(connection);
+        self.end_turn(&state).await
+    }
+
+    /// Send speech.config if it changed and the SSML of a new turn
+    async fn send_request(&mut self, text: &str, ssml: &str, context: SpeechContext) -> Result<()> {
+        let mut connection = self.connection.lock().await;
+        let connection = &mut *connection;
+        let websocket = connection
+            .websocket
+            .as_mut()
+            .ok_or(tungstenite::Error::AlreadyClosed)?;
+        if self.sent_context.as_ref() != Some(&context) {
+            websocket.send(build_context_message(&context)).await?;
+            self.sent_context = Some(context);
+        }
+        websocket
+            .send(build_ssml_message(
+                ssml,
+                self.request_id.as_deref().unwrap_or_default(),
+            ))
+            .await?;
+        self.in_turn = true;
+        connection.last_active = Instant::now();
+        UsageTracker::global().add_characters(text);
+        Ok(())
+    }
+
+    /// Mark the turn finished, forget the connection if the server closed it
+    async fn end_turn(&mut self, state: &TurnState) -> Result<()> {
+        self.in_turn = false;
+        if state.closed {
+            self.connection.lock().await.websocket = None;
+            self.sent_context = None;
+            if !state.is_complete() {
+                return Err(tungstenite::Error::ConnectionClosed.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Reconnect if the server closed the connection or a previous turn was abandoned
+    async fn ensure_connected(&mut self) -> Result<()> {
+        let mut connection = self.connection.lock().await;
+        if self.in_turn {
+            // frames of the abandoned turn would be read as the next one
+            connection.websocket = None;
+            self.in_turn = false;
+            if self.reconnect.is_none() {
+                return Err(Error::TurnAbandoned);
+            }
+        }
+        if connection.websocket.is_none() {
+            let reconnect = self
+                .reconnect
+                .as_mut()
+                .ok_or(tungstenite::Error::AlreadyClosed)?;
+            connection.websocket = Some(self.reconnect_policy.retry_async(reconnect).await?);
+            connection.last_active = Instant::now();
+            self.sent_context = None;
+        }
+        Ok(())
+    }
+}
+
+/// Errors after which the request may succeed on a new connection
+fn is_connection_lost(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::TungsteniteError(
+            tungstenite::Error::ConnectionClosed
+                | tungstenite::Error::AlreadyClosed
+                | tungstenite::Error::Io(_)
+                | tungstenite::Error::Protocol(
+                    tungstenite::error::ProtocolError::ResetWithoutClosingHandshake
+                )
+        )
+    )
+}
+
+async fn audio_bytes(response: SynthesizedResponse) -> Result<Option<Vec<u8>>> {
+    match response {
+        SynthesizedResponse::AudioBytes(bytes) => Ok(Some(bytes)),
+        SynthesizedResponse::AudioMetadata(_) => Ok(None),
+    }
+}
+
+/// [AsyncRead] over audio bytes of a turn stream
+fn audio_reader(
+    responses: impl Stream<Item = Result<SynthesizedResponse>>,
+) -> impl AsyncRead + Unpin {
+    Box::pin(responses.try_filter_map(audio_bytes).map_err(into_io_error)).into_async_read()
+}
+
+/// Keep io errors as they are for readers, wrap others
+fn into_io_error(error: Error) -> std::io::Error {
+    match error {
+        Error::IoError(error) => error,
+        error => std::io::Error::other(error),
+    }
+}
+
+/// Audio bytes of a sync synthesis turn as [Read], see [MSEdgeTTSClient::synthesize_reader]
+pub struct AudioReader<'a, T: Read + Write> {
+    client: &'a mut MSEdgeTTSClient<T>,
+    state: TurnState,
+    /// audio bytes of the last frame, read from `position`
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl<T: Read + Write> AudioReader<'_, T> {
+    /// Read frames until one carries audio bytes or the turn ends
+    fn read_frame(&mut self) -> Result<()> {
+        let mut connection = lock(&self.client.connection);
+        let websocket = connection
+            .websocket
+            .as_mut()
+            .ok_or(tungstenite::Error::AlreadyClosed)?;
+        while !self.state.turn_end {
+            let message = websocket.read()?;
+            if let Some(ProcessedMessage::AudioBytes((bytes, index))) =
+                process_message(message, &mut self.state)?
+            {
+                self.buffer = bytes;
+                self.position = index;
+                return Ok(());
+            }
+        }
+        drop(connection);
+        self.client.end_turn(&self.state)
+    }
+}
+
+impl<T: Read + Write> Read for AudioReader<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position >= self.buffer.len() {
+            if self.state.turn_end {
+                return Ok(0);
+            }
+            if let Err(error) = self.read_frame() {
+                // the turn can not be continued
+                self.state.turn_end = true;
+                return Err(into_io_error(error));
+            }
+        }
+        let len = buf.len().min(self.buffer.len() - self.position);
+        buf[..len].copy_from_slice(&self.buffer[self.position..self.position + len]);
+        self.position += len;
+        Ok(len)
+    }
+}
+
+/// Collect audio bytes and metadata of a turn into [SynthesizedAudio]
+#[derive(Default)]
+struct AudioCollector {
+    audio_bytes: Vec<u8>,
+    audio_metadata: Vec<AudioMetadata>,
+}
+
+impl AudioCollector {
+    fn push(&mut self, message: ProcessedMessage) {
+        match message {
+            ProcessedMessage::AudioBytes((bytes, index)) => {
+                self.audio_bytes.extend_from_slice(&bytes[index..]);
+            }
+            ProcessedMessage::AudioMetadata(metadata) => {
+                self.audio_metadata.extend(metadata);
+            }
+        }
+    }
 
-        Ok(SynthesizedAudio {
+    /// Build [SynthesizedAudio] from a finished turn, keep partial audio if the turn failed
+    fn finish(
+        self,
+        text: &str,
+        config: &SpeechConfig,
+        request_id: Option<String>,
+        result: Result<()>,
+    ) -> Result<SynthesizedAudio> {
+        let audio = SynthesizedAudio {
+            request_id: request_id.unwrap_or_default(),
+            text: text.to_owned(),
             audio_format: config.audio_format.clone(),
-            audio_bytes,
-            audio_metadata,
-        })
+            audio_bytes: self.audio_bytes,
+            audio_metadata: self.audio_metadata,
+        };
+        match result {
+            Ok(()) => Ok(audio),
+            Err(Error::Timeout { .. }) => Err(Error::Timeout {
+                partial: Some(Box::new(audio)),
+            }),
+            Err(error) if audio.audio_bytes.is_empty() => Err(error),
+            Err(error) => Err(Error::IncompleteSynthesis {
+                partial: Box::new(audio),
+                error: Box::new(error),
+            }),
+        }
+    }
+}
+
+/// Stream responses of one synthesis turn, `client` is borrowed or owned by the stream
+fn turn_stream<T, C>(
+    client: C,
+    text: &str,
+    config: &SpeechConfig,
+) -> impl Stream<Item = Result<SynthesizedResponse>>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+    C: BorrowMut<MSEdgeTTSClientAsync<T>>,
+{
+    let ssml = build_ssml(text, config);
+    let request = check_request(&ssml, config).map(|()| {
+        (
+            speech_context(config),
+            ssml,
+            text.to_owned(),
+            new_request_id(config),
+        )
+    });
+    futures_util::stream::unfold(
+        Some((client, Some(request), TurnState::default())),
+        |state| async move {
+            let (mut client, request, mut turn_state) = state?;
+            let inner = client.borrow_mut();
+            if let Some(request) = request {
+                let (context, ssml, text, request_id) = match request {
+                    Ok(request) => request,
+                    Err(err) => return Some((Err(err), None)),
+                };
+                inner.request_id = Some(request_id);
+                if let Some(ref limit) = inner.rate_limit {
+                    limit.wait_async().await;
+                }
+                if let Err(err) = inner.ensure_connected().await {
+                    return Some((Err(err), None));
+                }
+                if let Err(err) = inner.send_request(&text, &ssml, context).await {
+                    inner.connection.lock().await.websocket = None;
+                    return Some((Err(err), None));
+                }
+            }
+            let response = {
+                let mut connection = inner.connection.lock().await;
+                match connection.websocket.as_mut() {
+                    Some(websocket) => next_response(websocket, &mut turn_state).await,
+                    None => Err(tungstenite::Error::AlreadyClosed.into()),
+                }
+            };
+            match response {
+                Ok(Some(response)) => Some((Ok(response), Some((client, None, turn_state)))),
+                Ok(None) => match inner.end_turn(&turn_state).await {
+                    Ok(()) => None,
+                    Err(err) => Some((Err(err), None)),
+                },
+                Err(err) => Some((Err(err), None)),
+            }
+        },
+    )
+}
+
+/// Read frames until one carries audio bytes or metadata, `None` when the turn ended
+async fn next_response<T: AsyncRead + AsyncWrite + Unpin>(
+    websocket: &mut WebSocketStreamAsync<T>,
+    turn_state: &mut TurnState,
+) -> Result<Option<SynthesizedResponse>> {
+    while !turn_state.turn_end {
+        let message = websocket
+            .next()
+            .await
+            .ok_or(tungstenite::Error::ConnectionClosed)??;
+        if let Some(message) = process_message(message, turn_state)? {
+            return Ok(Some(message.into()));
+        }
     }
+    Ok(None)
 }
 
 /// Synthesized Audio and Metadata
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct SynthesizedAudio {
-    pub audio_format: String,
+    /// `X-RequestId` of the synthesis, to correlate with service side logs
+    #[serde(default)]
+    pub request_id: String,
+    /// the text or SSML document which was synthesized
+    pub text: String,
+    pub audio_format: AudioOutputFormat,
     pub audio_bytes: Vec<u8>,
     pub audio_metadata: Vec<AudioMetadata>,
 }
 
+impl SynthesizedAudio {
+    /// Total audio duration.
+    ///
+    /// Computed from the byte length for PCM, A-law and mu-law formats,
+    /// otherwise the end of the last boundary in [audio_metadata](Self::audio_metadata).
+    pub fn duration(&self) -> Duration {
+        let format = self.audio_format.as_str();
+        let (raw_format, header_bytes) = match format.strip_prefix("riff-") {
+            // canonical WAVE header
+            Some(rest) if self.audio_bytes.starts_with(b"RIFF") => (format!("raw-{rest}"), 44),
+            _ => (format.to_owned(), 0),
+        };
+        if let Some((encoding, sample_rate)) = parse_raw_audio_format(&raw_format) {
+            let bytes_per_sample = match encoding {
+                RawEncoding::Pcm16 => 2,
+                RawEncoding::ALaw | RawEncoding::MuLaw => 1,
+            };
+            let samples = self.audio_bytes.len().saturating_sub(header_bytes) / bytes_per_sample;
+            return Duration::from_secs_f64(samples as f64 / sample_rate as f64);
+        }
+        self.audio_metadata
+            .iter()
+            .map(|metadata| metadata.end())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Get word timings from `WordBoundary` metadata.
+    ///
+    /// `char_start` and `char_end` are char indices of the word in [text](Self::text).
+    pub fn word_timings(&self) -> Vec<WordTiming> {
+        let mut timings = Vec::new();
+        // byte index and char index of the search cursor
+        let mut cursor = 0;
+        let mut char_cursor = 0;
+        for metadata in &self.audio_metadata {
+            if metadata.metadata_type.as_deref() != Some("WordBoundary") {
+                continue;
+            }
+            let word = match metadata.text {
+                Some(ref word) => word,
+                None => continue,
+            };
+
+            let word_chars = word.chars().count();
+            let char_start = match self.text[cursor..].find(word.as_str()) {
+                Some(index) => {
+                    char_cursor += self.text[cursor..cursor + index].chars().count();
+                    cursor += index + word.len();
+                    let char_start = char_cursor;
+                    char_cursor += word_chars;
+                    char_start
+                }
+                // word not found in text, keep cursor unchanged
+                None => char_cursor,
+            };
+
+            timings.push(WordTiming {
+                word: word.clone(),
+                start_ms: metadata.offset / TICKS_PER_MILLISECOND,
+                end_ms: (metadata.offset + metadata.duration) / TICKS_PER_MILLISECOND,
+                char_start,
+                char_end: char_start + word_chars,
+            });
+        }
+        timings
+    }
+
+    /// Export [word timings](Self::word_timings) as a JSON array.
+    ///
+    /// Each entry is `{"word": .., "start_ms": .., "end_ms": .., "char_start": .., "char_end": ..}`.
+    pub fn to_timings_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.word_timings())?)
+    }
+
+    /// Compute [TextStatistics] from [audio_metadata](Self::audio_metadata)
+    pub fn text_statistics(&self) -> TextStatistics {
+        text_statistics(&self.audio_metadata)
+    }
+
+    /// Split [audio_metadata](Self::audio_metadata) into sentence [chapters](Chapter)
+    pub fn chapters(&self) -> Vec<Chapter> {
+        chapters(&self.audio_metadata)
+    }
+
+    /// Export [chapters](Self::chapters) as a JSON array.
+    ///
+    /// Each entry is `{"title": .., "start_ms": .., "end_ms": ..}`.
+    pub fn to_chapters_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.chapters())?)
+    }
+
+    /// Export [chapters](Self::chapters) as a CUE sheet for the audio saved at `file_name`
+    pub fn to_cue_sheet(&self, file_name: &str) -> String {
+        let file_type = match self.file_extension() {
+            "mp3" => "MP3",
+            "wav" => "WAVE",
+            _ => "BINARY",
+        };
+        cue_sheet(&self.chapters(), file_name, file_type)
+    }
+
+    /// File extension matching [audio_format](Self::audio_format).
+    ///
+    /// `raw-*` PCM, A-law and μ-law formats map to `wav` since [save](Self::save) wraps them in a WAV container.
+    pub fn file_extension(&self) -> &'static str {
+        let format = self.audio_format.as_str();
+        if parse_raw_audio_format(format).is_some() || format.starts_with("riff-") {
+            "wav"
+        } else if format.ends_with("-mp3") {
+            "mp3"
+        } else if format.starts_with("ogg-") {
+            "ogg"
+        } else if format.starts_with("webm-") {
+            "webm"
+        } else if format.ends_with("-opus") {
+            "opus"
+        } else if format.starts_with("amr-wb") {
+            "amr"
+        } else {
+            "raw"
+        }
+    }
+
+    /// Save audio bytes to a file.
+    ///
+    /// Encoded formats are written as they are, `raw-*` PCM, A-law and μ-law formats are wrapped in a WAV container.
+    /// If `path` has no extension, [file_extension](Self::file_extension) is appended.
+    /// Return the path of the written file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<PathBuf> {
+        let mut path = path.as_ref().to_path_buf();
+        if path.extension().is_none() {
+            path.set_extension(self.file_extension());
+        }
+
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+        if let Some((encoding, sample_rate)) = parse_raw_audio_format(self.audio_format.as_str()) {
+            file.write_all(&wav_header(
+                encoding,
+                sample_rate,
+                self.audio_bytes.len() as u32,
+            ))?;
+        }
+        file.write_all(&self.audio_bytes)?;
+        file.flush()?;
+        Ok(path)
+    }
+}
+
+/// RIFF (12), fmt chunk (24), data chunk header (8)
+fn wav_header(encoding: RawEncoding, sample_rate: u32, data_len: u32) -> Vec<u8> {
+    // format tag, bits per sample
+    let (format_tag, bits_per_sample): (u16, u16) = match encoding {
+        RawEncoding::Pcm16 => (0x0001, 16),
+        RawEncoding::ALaw => (0x0006, 8),
+        RawEncoding::MuLaw => (0x0007, 8),
+    };
+    let block_align = bits_per_sample / 8; // mono
+    let byte_rate = sample_rate * block_align as u32;
+
+    let mut bytes = Vec::with_capacity(44);
+    bytes.extend(b"RIFF");
+    bytes.extend((36 + data_len).to_le_bytes());
+    bytes.extend(b"WAVE");
+    bytes.extend(b"fmt ");
+    bytes.extend(16u32.to_le_bytes());
+    bytes.extend(format_tag.to_le_bytes());
+    bytes.extend(1u16.to_le_bytes()); // channels
+    bytes.extend(sample_rate.to_le_bytes());
+    bytes.extend(byte_rate.to_le_bytes());
+    bytes.extend(block_align.to_le_bytes());
+    bytes.extend(bits_per_sample.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend(data_len.to_le_bytes());
+    bytes
+}
+
+/// metadata offset and duration are in 100-nanosecond ticks
+const TICKS_PER_MILLISECOND: u64 = 10_000;
+
+/// Word Timing
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct WordTiming {
+    pub word: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+impl MSEdgeTTSClient<std::net::TcpStream> {
+    /// Create Sync TTS Client with timeouts and header overrides of `config`
+    pub fn connect_with(config: ConnectionConfig) -> Result<Self> {
+        let websocket = websocket_connect_with(&config)?;
+        Ok(Self::new(
+            websocket,
+            Some(Box::new(move || websocket_connect_with(&config))),
+            tcp_socket,
+        ))
+    }
+}
+
+impl MSEdgeTTSClientAsync<async_std::net::TcpStream> {
+    /// Create Async TTS Client with timeouts and header overrides of `config`
+    pub async fn connect_with(config: ConnectionConfig) -> Result<Self> {
+        let websocket = websocket_connect_with_async(&config).await?;
+        Ok(Self::new(
+            websocket,
+            Some(Box::new(move || {
+                let config = config.clone();
+                Box::pin(async move { websocket_connect_with_async(&config).await })
+            })),
+        ))
+    }
+}
+
+impl<T: Read + Write> MSEdgeTTSClient<T> {
+    /// Create Sync TTS Client over `stream`, already connected to the TTS server, e.g. through a tunnel
+    ///
+    /// Only the TLS and websocket handshakes are done over it.
+    /// The client can not reconnect, and its timeouts are only checked between frames.
+    pub fn from_stream(stream: T) -> Result<Self> {
+        Self::from_stream_with(stream, &ConnectionConfig::default())
+    }
+
+    /// Create Sync TTS Client over `stream` with the header overrides of `config`, see [from_stream](Self::from_stream)
+    pub fn from_stream_with(stream: T, config: &ConnectionConfig) -> Result<Self> {
+        Ok(Self::new(
+            websocket_handshake(stream, config)?,
+            None,
+            |_| None,
+        ))
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send + 'static> MSEdgeTTSClientAsync<T> {
+    /// Create Async TTS Client over `stream`, already connected to the TTS server, e.g. through a tunnel
+    ///
+    /// Only the TLS and websocket handshakes are done over it, the client can not reconnect.
+    pub async fn from_stream(stream: T) -> Result<Self> {
+        Self::from_stream_with(stream, &ConnectionConfig::default()).await
+    }
+
+    /// Create Async TTS Client over `stream` with the header overrides of `config`, see [from_stream](Self::from_stream)
+    pub async fn from_stream_with(stream: T, config: &ConnectionConfig) -> Result<Self> {
+        Ok(Self::new(
+            websocket_handshake_async(stream, config).await?,
+            None,
+        ))
+    }
+}
+
 /// Create Sync TTS [Client](MSEdgeTTSClient)
 pub fn connect() -> Result<MSEdgeTTSClient<std::net::TcpStream>> {
-    Ok(MSEdgeTTSClient(websocket_connect()?))
+    Ok(MSEdgeTTSClient::new(
+        websocket_connect()?,
+        Some(Box::new(websocket_connect)),
+        tcp_socket,
+    ))
 }
 
 /// Create Sync TTS [Client](MSEdgeTTSClient) with proxy
@@ -146,14 +1763,90 @@ pub fn connect_proxy(
     username: Option<&str>,
     password: Option<&str>,
 ) -> Result<MSEdgeTTSClient<ProxyStream>> {
-    Ok(MSEdgeTTSClient(websocket_connect_proxy(
-        proxy, username, password,
-    )?))
+    connect_proxy_with(proxy, username, password, ConnectionConfig::default())
+}
+
+/// Create Sync TTS [Client](MSEdgeTTSClient) with proxy and the header, TLS and 403 retry options of `config`,
+/// see [connect_proxy].
+///
+/// The timeouts of `config` are not applied to proxied connections.
+pub fn connect_proxy_with(
+    proxy: http::Uri,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: ConnectionConfig,
+) -> Result<MSEdgeTTSClient<ProxyStream>> {
+    let websocket = websocket_connect_proxy(proxy.clone(), username, password, &config)?;
+    let (username, password) = (username.map(str::to_owned), password.map(str::to_owned));
+    Ok(MSEdgeTTSClient::new(
+        websocket,
+        Some(Box::new(move || {
+            websocket_connect_proxy(
+                proxy.clone(),
+                username.as_deref(),
+                password.as_deref(),
+                &config,
+            )
+        })),
+        proxy_socket,
+    ))
+}
+
+fn tcp_socket(websocket: &WebSocketStream<std::net::TcpStream>) -> Option<&std::net::TcpStream> {
+    match websocket.get_ref() {
+        tungstenite::stream::MaybeTlsStream::Plain(stream) => Some(stream),
+        tungstenite::stream::MaybeTlsStream::NativeTls(stream) => Some(stream.get_ref()),
+        _ => None,
+    }
+}
+
+fn proxy_socket(websocket: &WebSocketStream<ProxyStream>) -> Option<&std::net::TcpStream> {
+    match websocket.get_ref() {
+        tungstenite::stream::MaybeTlsStream::Plain(stream) => Some(stream.get_ref()),
+        tungstenite::stream::MaybeTlsStream::NativeTls(stream) => Some(stream.get_ref().get_ref()),
+        _ => None,
+    }
 }
 
 /// Create Async TTS [Client](MSEdgeTTSClientAsync)
 pub async fn connect_async() -> Result<MSEdgeTTSClientAsync<async_std::net::TcpStream>> {
-    Ok(MSEdgeTTSClientAsync(websocket_connect_async().await?))
+    Ok(MSEdgeTTSClientAsync::new(
+        websocket_connect_async().await?,
+        Some(Box::new(|| Box::pin(websocket_connect_async()))),
+    ))
+}
+
+/// Synthesize many texts with a [SpeechConfig] asynchronously, using at most `max_concurrency` connections.
+///
+/// Results are returned in the same order as `texts`.
+/// A connection which fails to synthesize is dropped and a new one is opened for the next text.
+/// If a connection can not be established, or a synthesis fails with an error which is neither
+/// [transient](Error::is_transient) nor a timeout, e.g. [Error::InvalidSpeechConfig] or [Error::TextTooLong],
+/// the remaining texts are cancelled with [Error::Cancelled].
+pub async fn synthesize_many<S: AsRef<str> + Sync>(
+    texts: &[S],
+    config: &SpeechConfig,
+    max_concurrency: usize,
+) -> Vec<Result<SynthesizedAudio>> {
+    synthesize_all(texts.len(), max_concurrency, true, |index| {
+        (texts[index].as_ref(), config)
+    })
+    .await
+}
+
+/// Synthesize text of any length with a [SpeechConfig], using at most `max_concurrency` connections.
+///
+/// Like [MSEdgeTTSClientAsync::synthesize_long], but the chunks are synthesized concurrently by [synthesize_many].
+pub async fn synthesize_long_parallel(
+    text: &str,
+    config: &SpeechConfig,
+    max_concurrency: usize,
+) -> Result<SynthesizedAudio> {
+    let chunks = synthesize_many(&split_text(text, config), config, max_concurrency).await;
+    Ok(stitch(
+        config.audio_format.clone(),
+        chunks.into_iter().collect::<Result<Vec<_>>>()?,
+    ))
 }
 
 /// Create Async TTS [Client](MSEdgeTTSClientAsync) with proxy
@@ -171,7 +1864,36 @@ pub async fn connect_proxy_async(
     username: Option<&str>,
     password: Option<&str>,
 ) -> Result<MSEdgeTTSClientAsync<ProxyAsyncStream>> {
-    Ok(MSEdgeTTSClientAsync(
-        websocket_connect_proxy_async(proxy, username, password).await?,
+    connect_proxy_with_async(proxy, username, password, ConnectionConfig::default()).await
+}
+
+/// Create Async TTS [Client](MSEdgeTTSClientAsync) with proxy and the header, TLS and 403 retry options of `config`,
+/// see [connect_proxy_async].
+///
+/// The handshake timeout of `config` is applied, the connect timeout is not.
+pub async fn connect_proxy_with_async(
+    proxy: http::Uri,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: ConnectionConfig,
+) -> Result<MSEdgeTTSClientAsync<ProxyAsyncStream>> {
+    let websocket =
+        websocket_connect_proxy_async(proxy.clone(), username, password, &config).await?;
+    let (username, password) = (username.map(str::to_owned), password.map(str::to_owned));
+    Ok(MSEdgeTTSClientAsync::new(
+        websocket,
+        Some(Box::new(move || {
+            let (proxy, username, password) = (proxy.clone(), username.clone(), password.clone());
+            let config = config.clone();
+            Box::pin(async move {
+                websocket_connect_proxy_async(
+                    proxy,
+                    username.as_deref(),
+                    password.as_deref(),
+                    &config,
+                )
+                .await
+            })
+        })),
     ))
 }
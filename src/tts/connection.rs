@@ -0,0 +1,234 @@
+//! Websocket connection options
+
+use crate::retry::RetryPolicy;
+use std::time::Duration;
+
+/// Options of the websocket connection to the TTS service
+///
+/// Defaults to the headers of MS Edge without timeouts.
+/// User-Agent, Origin and Sec-MS-GEC-Version can be overridden per client when the service requires newer values.
+/// Proxied clients of [connect_proxy_with](super::client::connect_proxy_with) apply all options but the timeouts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionConfig {
+    pub(super) connect_timeout: Option<Duration>,
+    pub(super) handshake_timeout: Option<Duration>,
+    pub(super) user_agent: Option<String>,
+    pub(super) origin: Option<String>,
+    pub(super) sec_ms_gec_version: Option<String>,
+    pub(super) headers: Vec<(String, String)>,
+    pub(super) forbidden_retry: RetryPolicy,
+    pub(super) tls: Option<TlsConfig>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            handshake_timeout: None,
+            user_agent: None,
+            origin: None,
+            sec_ms_gec_version: None,
+            headers: Vec::new(),
+            forbidden_retry: RetryPolicy::forbidden(),
+            tls: None,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timeout of establishing the TCP connection
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout of the TLS and websocket handshakes
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = Some(timeout);
+        self
+    }
+
+    /// Override the `User-Agent` header
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Override the `Origin` header
+    pub fn origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
+
+    /// Override the `Sec-MS-GEC-Version` query parameter, e.g. `1-131.0.2903.99` matching the version of the User-Agent
+    pub fn sec_ms_gec_version(mut self, version: impl Into<String>) -> Self {
+        self.sec_ms_gec_version = Some(version.into());
+        self
+    }
+
+    /// Retry of handshakes rejected with 403, each attempt sends a fresh `Sec-MS-GEC`.
+    ///
+    /// Default [RetryPolicy::forbidden], [RetryPolicy::none] disables it.
+    pub fn forbidden_retry(mut self, policy: RetryPolicy) -> Self {
+        self.forbidden_retry = policy;
+        self
+    }
+
+    /// TLS options, e.g. a corporate root CA or a client certificate
+    pub fn tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Add a header to the websocket request, replacing the default header of the same name
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// TLS options of the websocket connection, used by sync and async clients
+///
+/// Certificates are parsed when connecting, invalid ones fail the connection.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TlsConfig {
+    root_certificates: Vec<CertificateData>,
+    identity: Option<IdentityData>,
+    accept_invalid_certs: bool,
+    accept_invalid_hostnames: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CertificateData {
+    Pem(Vec<u8>),
+    Der(Vec<u8>),
+}
+
+#[derive(Clone, PartialEq)]
+enum IdentityData {
+    Pkcs12 { der: Vec<u8>, password: String },
+    Pkcs8 { pem: Vec<u8>, key: Vec<u8> },
+}
+
+impl std::fmt::Debug for IdentityData {
+    /// Keys and passwords are redacted
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IdentityData::Pkcs12 { der, .. } => f
+                .debug_struct("Pkcs12")
+                .field("der", &format_args!("<{} bytes>", der.len()))
+                .field("password", &"<redacted>")
+                .finish(),
+            IdentityData::Pkcs8 { pem, .. } => f
+                .debug_struct("Pkcs8")
+                .field("pem", &format_args!("<{} bytes>", pem.len()))
+                .field("key", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust a PEM encoded root certificate in addition to the system ones
+    pub fn root_certificate_pem(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates
+            .push(CertificateData::Pem(pem.into()));
+        self
+    }
+
+    /// Trust a DER encoded root certificate in addition to the system ones
+    pub fn root_certificate_der(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates
+            .push(CertificateData::Der(der.into()));
+        self
+    }
+
+    /// Client certificate from a DER encoded PKCS #12 archive
+    pub fn identity_pkcs12(mut self, der: impl Into<Vec<u8>>, password: impl Into<String>) -> Self {
+        self.identity = Some(IdentityData::Pkcs12 {
+            der: der.into(),
+            password: password.into(),
+        });
+        self
+    }
+
+    /// Client certificate chain and PKCS #8 key, both PEM encoded
+    pub fn identity_pkcs8(mut self, pem: impl Into<Vec<u8>>, key: impl Into<Vec<u8>>) -> Self {
+        self.identity = Some(IdentityData::Pkcs8 {
+            pem: pem.into(),
+            key: key.into(),
+        });
+        self
+    }
+
+    /// Accept any server certificate.
+    ///
+    /// **Caution**: the connection is open to man-in-the-middle attacks, only use it in controlled environments.
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Accept server certificates of another host name, see [danger_accept_invalid_certs](Self::danger_accept_invalid_certs)
+    pub fn danger_accept_invalid_hostnames(mut self, accept: bool) -> Self {
+        self.accept_invalid_hostnames = accept;
+        self
+    }
+
+    pub(super) fn connector(&self) -> native_tls::Result<native_tls::TlsConnector> {
+        let mut builder = native_tls::TlsConnector::builder();
+        for certificate in self.certificates()? {
+            builder.add_root_certificate(certificate);
+        }
+        if let Some(identity) = self.identity()? {
+            builder.identity(identity);
+        }
+        builder
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .danger_accept_invalid_hostnames(self.accept_invalid_hostnames)
+            .build()
+    }
+
+    pub(super) fn connector_async(&self) -> native_tls::Result<async_native_tls::TlsConnector> {
+        let mut connector = async_native_tls::TlsConnector::new();
+        for certificate in self.certificates()? {
+            connector = connector.add_root_certificate(certificate);
+        }
+        if let Some(identity) = self.identity()? {
+            connector = connector.identity(identity);
+        }
+        Ok(connector
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .danger_accept_invalid_hostnames(self.accept_invalid_hostnames))
+    }
+
+    fn certificates(&self) -> native_tls::Result<Vec<native_tls::Certificate>> {
+        self.root_certificates
+            .iter()
+            .map(|certificate| match certificate {
+                CertificateData::Pem(pem) => native_tls::Certificate::from_pem(pem),
+                CertificateData::Der(der) => native_tls::Certificate::from_der(der),
+            })
+            .collect()
+    }
+
+    fn identity(&self) -> native_tls::Result<Option<native_tls::Identity>> {
+        self.identity
+            .as_ref()
+            .map(|identity| match identity {
+                IdentityData::Pkcs12 { der, password } => {
+                    native_tls::Identity::from_pkcs12(der, password)
+                }
+                IdentityData::Pkcs8 { pem, key } => native_tls::Identity::from_pkcs8(pem, key),
+            })
+            .transpose()
+    }
+}
@@ -0,0 +1,84 @@
+//! Typed `speech.config` message body.
+//!
+//! [SpeechContext::from] a [SpeechConfig] gives the body sent by default.
+//! Set [SpeechConfig::speech_context] to send a customized one, e.g. with fields this crate does not know yet
+//! in the `extra` maps, which are flattened into their parent object.
+
+use super::{AudioOutputFormat, MetadataOptions, SpeechConfig};
+
+/// `context` object of `speech.config`
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SpeechContext {
+    pub synthesis: SynthesisContext,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `context.synthesis` object of `speech.config`
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SynthesisContext {
+    pub audio: AudioContext,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// `context.synthesis.audio` object of `speech.config`
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct AudioContext {
+    #[serde(rename = "metadataoptions", with = "metadata_options")]
+    pub metadata_options: MetadataOptions,
+    #[serde(rename = "outputFormat")]
+    pub output_format: AudioOutputFormat,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl From<&SpeechConfig> for SpeechContext {
+    fn from(config: &SpeechConfig) -> Self {
+        Self {
+            synthesis: SynthesisContext {
+                audio: AudioContext {
+                    metadata_options: config.metadata_options,
+                    output_format: config.audio_format.clone(),
+                    extra: serde_json::Map::new(),
+                },
+                extra: serde_json::Map::new(),
+            },
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+/// The service expects camelCase keys and booleans as strings, `{"wordBoundaryEnabled":"true"}`
+mod metadata_options {
+    use super::MetadataOptions;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Wire {
+        sentence_boundary_enabled: String,
+        word_boundary_enabled: String,
+    }
+
+    pub fn serialize<S: Serializer>(
+        options: &MetadataOptions,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Wire {
+            sentence_boundary_enabled: options.sentence_boundary_enabled.to_string(),
+            word_boundary_enabled: options.word_boundary_enabled.to_string(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<MetadataOptions, D::Error> {
+        let wire = Wire::deserialize(deserializer)?;
+        Ok(MetadataOptions {
+            sentence_boundary_enabled: wire.sentence_boundary_enabled == "true",
+            word_boundary_enabled: wire.word_boundary_enabled == "true",
+        })
+    }
+}
@@ -0,0 +1,90 @@
+//! Pre-synthesis size and duration estimation
+//!
+//! Use [estimate] to get approximate audio duration and size of a text before synthesizing it.
+//! The numbers are rough heuristics based on character counts, [rate](SpeechConfig::rate) and the bitrate of
+//! [audio_format](SpeechConfig::audio_format), good enough for progress bars and batch planning.
+
+use super::{parse_raw_audio_format, RawEncoding, SpeechConfig};
+use std::time::Duration;
+
+/// seconds per CJK character at default rate
+const CJK_CHAR_SECONDS: f64 = 0.22;
+/// seconds per other character (including spaces) at default rate
+const OTHER_CHAR_SECONDS: f64 = 0.065;
+/// extra pause after sentence punctuation
+const PUNCTUATION_PAUSE_SECONDS: f64 = 0.3;
+
+/// Estimated synthesis result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    pub approx_duration: Duration,
+    pub approx_bytes: u64,
+}
+
+/// Estimate audio duration and size of `text` synthesized with `config`
+pub fn estimate(text: &str, config: &SpeechConfig) -> Estimate {
+    let mut seconds = 0.0;
+    for c in text.trim().chars() {
+        if is_cjk(c) {
+            seconds += CJK_CHAR_SECONDS;
+        } else if matches!(c, '.' | '!' | '?' | ';' | '。' | '！' | '？' | '；') {
+            seconds += PUNCTUATION_PAUSE_SECONDS;
+        } else {
+            seconds += OTHER_CHAR_SECONDS;
+        }
+    }
+    seconds /= config.rate.speed();
+    if let Some(duration) = config.audio_duration {
+        seconds = duration.as_secs_f64();
+    }
+
+    let approx_bytes = (seconds * bitrate(config.audio_format.as_str()) as f64 / 8.0) as u64;
+    let header_bytes = if config.audio_format.as_str().starts_with("riff-") {
+        44
+    } else {
+        0
+    };
+    Estimate {
+        approx_duration: Duration::from_secs_f64(seconds),
+        approx_bytes: approx_bytes + header_bytes,
+    }
+}
+
+/// bits per second of an audio output format
+pub(super) fn bitrate(audio_format: &str) -> u64 {
+    for part in audio_format.split('-') {
+        if let Some(kbps) = part
+            .strip_suffix("kbitrate")
+            .or_else(|| part.strip_suffix("kbps"))
+        {
+            if let Ok(kbps) = kbps.parse::<u64>() {
+                return kbps * 1000;
+            }
+        }
+    }
+
+    // riff-* formats carry the same samples as raw-* formats
+    let raw_format = audio_format.replacen("riff-", "raw-", 1);
+    if let Some((encoding, sample_rate)) = parse_raw_audio_format(&raw_format) {
+        return match encoding {
+            RawEncoding::Pcm16 => sample_rate as u64 * 16,
+            RawEncoding::ALaw | RawEncoding::MuLaw => sample_rate as u64 * 8,
+        };
+    }
+
+    // variable bitrate codecs, use typical values
+    if audio_format.starts_with("amr-wb") {
+        24_000
+    } else {
+        32_000
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30ff}' // Hiragana, Katakana
+        | '\u{3400}'..='\u{4dbf}' // CJK Unified Ideographs Extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK Unified Ideographs
+        | '\u{ac00}'..='\u{d7af}' // Hangul Syllables
+    )
+}
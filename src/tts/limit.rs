@@ -0,0 +1,81 @@
+//! Rate limit module
+//!
+//! [RateLimit] spaces out synthesis requests so heavy batch use does not trip the throttling of the service.
+//! Set it on a [client](super::client::MSEdgeTTSClient::rate_limit) or a [pool](super::pool::TTSPool::rate_limit),
+//! clones share their budget, so one limit can cover many clients.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Request rate limit
+///
+/// Without limits set, requests are never delayed.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimit {
+    max_per_minute: Option<u32>,
+    min_gap: Option<Duration>,
+    /// send time of recent requests, in order, including reserved future ones
+    sent: Arc<Mutex<VecDeque<Instant>>>,
+}
+
+impl RateLimit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// At most `max` requests in any minute
+    pub fn max_per_minute(mut self, max: u32) -> Self {
+        self.max_per_minute = Some(max.max(1));
+        self
+    }
+
+    /// At least `gap` between two requests
+    pub fn min_gap(mut self, gap: Duration) -> Self {
+        self.min_gap = Some(gap);
+        self
+    }
+
+    /// Block until a request may be sent
+    pub fn wait(&self) {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+
+    /// Wait asynchronously until a request may be sent
+    pub async fn wait_async(&self) {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            async_io::Timer::after(delay).await;
+        }
+    }
+
+    /// Reserve the earliest send time allowed by the limits, return the delay until it
+    fn reserve(&self) -> Duration {
+        let now = Instant::now();
+        let mut sent = self.sent.lock().unwrap();
+        // reserved times never go backwards, so the queue stays in order
+        let mut at = sent.back().map_or(now, |&last| now.max(last));
+        if let (Some(gap), Some(&last)) = (self.min_gap, sent.back()) {
+            at = at.max(last + gap);
+        }
+        if let Some(max) = self.max_per_minute {
+            let max = max as usize;
+            if sent.len() >= max {
+                at = at.max(sent[sent.len() - max] + MINUTE);
+            }
+        }
+        sent.push_back(at);
+        let keep = self.max_per_minute.unwrap_or(1) as usize;
+        while sent.len() > keep {
+            sent.pop_front();
+        }
+        at - now
+    }
+}
@@ -0,0 +1,158 @@
+//! Long text module
+//!
+//! Use [split_text] to split a text on sentence boundaries into chunks which fit in one request,
+//! and [stitch] to concatenate the [SynthesizedAudio] of the chunks with rebased metadata offsets.
+//! [synthesize_long](super::client::MSEdgeTTSClient::synthesize_long) of the clients does both.
+
+use super::{
+    build_ssml, client::SynthesizedAudio, estimate::bitrate, render_text, AudioOutputFormat,
+    SpeechConfig, DEFAULT_MAX_SSML_BYTES,
+};
+
+/// ticks of 100 nanoseconds per second, the unit of [AudioMetadata](super::AudioMetadata) offsets
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+/// length of the canonical RIFF WAVE header
+const RIFF_HEADER_BYTES: usize = 44;
+
+/// Split `text` into chunks whose SSML fits in [max_ssml_bytes](SpeechConfig::max_ssml_bytes).
+///
+/// Chunks end on sentence boundaries, a sentence too long for one chunk is split between words,
+/// and a word too long between chars. Concatenated chunks are exactly `text`.
+pub fn split_text(text: &str, config: &SpeechConfig) -> Vec<String> {
+    let limit = config.max_ssml_bytes.unwrap_or(DEFAULT_MAX_SSML_BYTES);
+    let budget = limit.saturating_sub(build_ssml("", config).len()).max(1);
+    let mut chunks = Vec::new();
+    pack(&sentences(text), 0, budget, config, &mut chunks);
+    chunks
+}
+
+/// Pack `pieces` into chunks of at most `budget` rendered bytes, split pieces too long at a finer `level`
+fn pack(
+    pieces: &[&str],
+    level: usize,
+    budget: usize,
+    config: &SpeechConfig,
+    chunks: &mut Vec<String>,
+) {
+    let mut chunk = String::new();
+    let mut chunk_bytes = 0;
+    for piece in pieces {
+        let piece_bytes = render_text(piece, config).len();
+        if chunk_bytes + piece_bytes <= budget {
+            chunk.push_str(piece);
+            chunk_bytes += piece_bytes;
+            continue;
+        }
+        if !chunk.is_empty() {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_bytes = 0;
+        }
+        if piece_bytes <= budget {
+            chunk.push_str(piece);
+            chunk_bytes = piece_bytes;
+            continue;
+        }
+        let finer: Vec<&str> = match level {
+            0 => piece.split_inclusive(char::is_whitespace).collect(),
+            1 => piece
+                .char_indices()
+                .map(|(i, c)| &piece[i..i + c.len_utf8()])
+                .collect(),
+            // a single char longer than the budget, it can not be split further
+            _ => {
+                chunks.push(piece.to_string());
+                continue;
+            }
+        };
+        pack(&finer, level + 1, budget, config, chunks);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+}
+
+/// Split text after sentence punctuation, keeping the following whitespace with the sentence
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let boundary = match c {
+            '。' | '！' | '？' | '；' | '\n' => true,
+            '.' | '!' | '?' | ';' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if boundary {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, next)) = chars.peek() {
+                if !next.is_whitespace() {
+                    break;
+                }
+                end = j + next.len_utf8();
+                chars.next();
+            }
+            sentences.push(&text[start..end]);
+            start = end;
+        }
+    }
+    if start < text.len() {
+        sentences.push(&text[start..]);
+    }
+    sentences
+}
+
+/// Concatenate synthesized chunks into one [SynthesizedAudio].
+///
+/// Metadata offsets of each chunk are shifted by the audio duration of the previous chunks,
+/// computed from the bitrate of `audio_format`.
+/// RIFF chunks are merged into one WAVE file, other formats are concatenated as they are.
+pub fn stitch(
+    audio_format: AudioOutputFormat,
+    chunks: impl IntoIterator<Item = SynthesizedAudio>,
+) -> SynthesizedAudio {
+    let riff = audio_format.as_str().starts_with("riff-");
+    let bits_per_second = bitrate(audio_format.as_str());
+    let mut audio = SynthesizedAudio {
+        request_id: String::new(),
+        text: String::new(),
+        audio_format,
+        audio_bytes: Vec::new(),
+        audio_metadata: Vec::new(),
+    };
+    let mut offset = 0;
+    for chunk in chunks {
+        let mut bytes = &chunk.audio_bytes[..];
+        if riff && is_wave_header(bytes) {
+            bytes = &bytes[RIFF_HEADER_BYTES..];
+            if audio.audio_bytes.is_empty() {
+                audio
+                    .audio_bytes
+                    .extend_from_slice(&chunk.audio_bytes[..RIFF_HEADER_BYTES]);
+            }
+        }
+        if audio.request_id.is_empty() {
+            audio.request_id = chunk.request_id;
+        }
+        audio.text.push_str(&chunk.text);
+        audio.audio_bytes.extend_from_slice(bytes);
+        audio
+            .audio_metadata
+            .extend(chunk.audio_metadata.into_iter().map(|mut metadata| {
+                metadata.offset += offset;
+                metadata
+            }));
+        offset += bytes.len() as u64 * 8 * TICKS_PER_SECOND / bits_per_second;
+    }
+    if riff && is_wave_header(&audio.audio_bytes) {
+        let riff_size = (audio.audio_bytes.len() - 8) as u32;
+        let data_size = (audio.audio_bytes.len() - RIFF_HEADER_BYTES) as u32;
+        audio.audio_bytes[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        audio.audio_bytes[40..44].copy_from_slice(&data_size.to_le_bytes());
+    }
+    audio
+}
+
+fn is_wave_header(bytes: &[u8]) -> bool {
+    bytes.len() >= RIFF_HEADER_BYTES && &bytes[..4] == b"RIFF" && &bytes[36..40] == b"data"
+}
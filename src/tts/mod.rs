@@ -1,9 +1,27 @@
 //! TTS Client and Stream, SpeechConfig, Response Type.
 
+pub mod batch;
+pub mod chapter;
 pub mod client;
+pub mod connection;
+pub mod context;
+pub mod estimate;
+pub mod limit;
+pub mod long;
+pub mod pipe;
+pub mod pool;
+pub mod prosody;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod statistics;
 pub mod stream;
+#[cfg(feature = "tokio-compat")]
+pub mod tokio_compat;
+pub mod usage;
 
 mod proxy;
+pub use prosody::{Pitch, Rate, Volume};
+
 use crate::error::{Error, ProxyError, Result};
 use proxy::{
     http_proxy, http_proxy_async, socks4_proxy, socks4_proxy_async, socks5_proxy,
@@ -13,74 +31,964 @@ use proxy::{
 use sha2::Digest;
 
 /// Synthesis Config
-#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct SpeechConfig {
     pub voice_name: String,
-    /// should be one of Streaming or NonStreaming audio output formats.
+    /// should be one of [Streaming](AudioOutputFormat::STREAMING) or [NonStreaming](AudioOutputFormat::NON_STREAMING) audio output formats.
+    ///
+    /// serialized as the format string, e.g. `audio-24khz-48kbitrate-mono-mp3`.
+    pub audio_format: AudioOutputFormat,
+    /// relative Hz from a number, `+2st`, `-10%` or a named preset like `high`
+    pub pitch: Pitch,
+    /// relative percent from a number, or a named preset like `x-slow`
+    pub rate: Rate,
+    /// relative percent from a number, `+6dB` or a named preset like `loud`
+    pub volume: Volume,
+    /// target duration of the synthesized audio, speech is stretched or compressed to fit it.
+    ///
+    /// emitted as `<mstts:audioduration>`, the service accepts 0.5x to 2x of the natural duration.
+    #[serde(default)]
+    pub audio_duration: Option<std::time::Duration>,
+    /// URIs of external PLS pronunciation lexicons, emitted as `<lexicon uri='...'/>` in the voice element.
+    #[serde(default)]
+    pub lexicon_uris: Vec<String>,
+    /// speaking style like `cheerful`, `newscast` or `whispering`, emitted as `<mstts:express-as style='...'>`.
+    ///
+    /// not every voice supports styles, unsupported styles are ignored by the service.
+    #[serde(default)]
+    pub style: Option<String>,
+    /// intensity of [style](Self::style), in [STYLE_DEGREE_RANGE], `1` is the default intensity.
+    #[serde(default)]
+    pub style_degree: Option<f32>,
+    /// role-play age and gender like `Girl` or `OlderAdultMale`, one of [SPEAKING_ROLES].
+    #[serde(default)]
+    pub role: Option<String>,
+    /// IPA pronunciation overrides of whole words, e.g. `"Nginx" => "ˈɛndʒɪnˈɛks"`,
+    /// emitted as `<phoneme alphabet='ipa' ph='...'>`. Words are matched case-sensitively.
+    #[serde(default)]
+    pub phonemes: std::collections::HashMap<String, String>,
+    /// `xml:lang` of the `<speak>` element, e.g. `zh-CN`, it affects pronunciation and `say-as` of some voices.
+    #[serde(default = "default_lang")]
+    pub lang: String,
+    /// metadata events requested in `speech.config`
+    #[serde(default)]
+    pub metadata_options: MetadataOptions,
+    /// silence inserted around text and at punctuation, emitted as `<mstts:silence type='...' value='...ms'/>`.
+    #[serde(default)]
+    pub silences: Vec<Silence>,
+    /// customized `speech.config` body, sent instead of the one derived from
+    /// [audio_format](Self::audio_format) and [metadata_options](Self::metadata_options).
+    #[serde(default)]
+    pub speech_context: Option<context::SpeechContext>,
+    /// longest SSML document in bytes sent in one request, default [DEFAULT_MAX_SSML_BYTES].
+    /// Longer requests fail with [Error::TextTooLong] instead of being dropped by the service.
+    #[serde(default)]
+    pub max_ssml_bytes: Option<usize>,
+    /// `X-RequestId` sent with the SSML, a new id is generated for each request if `None`.
+    /// A fixed id is sent with every request using this config.
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+/// Default of [SpeechConfig::max_ssml_bytes], the service drops larger websocket messages
+pub const DEFAULT_MAX_SSML_BYTES: usize = 64 * 1024 - 1024;
+
+/// Checks of every request, which would otherwise send a broken request.
+///
+/// Return [Error::InvalidSpeechConfig] if the voice name contains XML special characters,
+/// and [Error::TextTooLong] if `ssml` is longer than [SpeechConfig::max_ssml_bytes].
+/// The other checks of [SpeechConfig::validate] are only done by [SpeechConfigBuilder::build].
+fn check_request(ssml: &str, config: &SpeechConfig) -> Result<()> {
+    check_voice_name(&config.voice_name)?;
+    let limit = config.max_ssml_bytes.unwrap_or(DEFAULT_MAX_SSML_BYTES);
+    if ssml.len() > limit {
+        return Err(Error::TextTooLong {
+            limit,
+            actual: ssml.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Longest [Silence] the service accepts
+pub const MAX_SILENCE: std::time::Duration = std::time::Duration::from_secs(20);
+
+/// Silence inserted by the service, see [SpeechConfig::silences]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub struct Silence {
+    pub silence_type: SilenceType,
+    /// at most [MAX_SILENCE]
+    pub duration: std::time::Duration,
+}
+
+/// Where a [Silence] is inserted.
+///
+/// Non exact types add to the natural silence, exact types replace it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum SilenceType {
+    /// before the text
+    Leading,
+    LeadingExact,
+    /// after the text
+    Tailing,
+    TailingExact,
+    /// between sentences
+    SentenceBoundary,
+    SentenceBoundaryExact,
+    /// at half width or full width commas
+    CommaExact,
+    /// at half width or full width semicolons
+    SemicolonExact,
+    /// at full width enumeration commas
+    EnumerationCommaExact,
+}
+
+impl SilenceType {
+    /// `type` attribute value of `<mstts:silence>`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SilenceType::Leading => "Leading",
+            SilenceType::LeadingExact => "Leading-exact",
+            SilenceType::Tailing => "Tailing",
+            SilenceType::TailingExact => "Tailing-exact",
+            SilenceType::SentenceBoundary => "Sentenceboundary",
+            SilenceType::SentenceBoundaryExact => "Sentenceboundary-exact",
+            SilenceType::CommaExact => "Comma-exact",
+            SilenceType::SemicolonExact => "Semicolon-exact",
+            SilenceType::EnumerationCommaExact => "Enumerationcomma-exact",
+        }
+    }
+}
+
+/// Metadata events sent by the service along with audio
+///
+/// Disable both for pure audio pipelines, the service then sends no `audio.metadata` messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct MetadataOptions {
+    /// receive `SentenceBoundary` [AudioMetadata], default `false`
+    pub sentence_boundary_enabled: bool,
+    /// receive `WordBoundary` [AudioMetadata], default `true`
+    pub word_boundary_enabled: bool,
+}
+
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self {
+            sentence_boundary_enabled: false,
+            word_boundary_enabled: true,
+        }
+    }
+}
+
+fn default_lang() -> String {
+    "en-US".to_owned()
+}
+
+/// Get locale from a voice name,
+/// e.g. `zh-CN` from `zh-CN-YunyangNeural` or `Microsoft Server Speech Text to Speech Voice (zh-CN, YunyangNeural)`
+fn locale_from_voice_name(voice_name: &str) -> Option<String> {
+    let name = match voice_name.split_once('(') {
+        Some((_, rest)) => rest.split(',').next()?.trim(),
+        None => voice_name,
+    };
+    let mut parts = name.splitn(3, '-');
+    let language = parts.next()?;
+    let region = parts.next()?;
+    if language.len() >= 2
+        && language.chars().all(|c| c.is_ascii_lowercase())
+        && region.chars().all(|c| c.is_ascii_alphanumeric())
+    {
+        Some(format!("{}-{}", language, region))
+    } else {
+        None
+    }
+}
+
+/// Audio Output Format
+///
+/// Streaming formats can be played while receiving, NonStreaming (`riff-*`) formats carry a WAV header.
+/// Use [Custom](Self::Custom) for formats the service added after this release.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(from = "String", into = "String")]
+pub enum AudioOutputFormat {
+    /// `amr-wb-16000hz`
+    AmrWb16000Hz,
+    /// `audio-16khz-16bit-32kbps-mono-opus`
+    Audio16Khz16Bit32KbpsMonoOpus,
+    /// `audio-16khz-32kbitrate-mono-mp3`
+    Audio16Khz32KBitRateMonoMp3,
+    /// `audio-16khz-64kbitrate-mono-mp3`
+    Audio16Khz64KBitRateMonoMp3,
+    /// `audio-16khz-128kbitrate-mono-mp3`
+    Audio16Khz128KBitRateMonoMp3,
+    /// `audio-24khz-16bit-24kbps-mono-opus`
+    Audio24Khz16Bit24KbpsMonoOpus,
+    /// `audio-24khz-16bit-48kbps-mono-opus`
+    Audio24Khz16Bit48KbpsMonoOpus,
+    /// `audio-24khz-48kbitrate-mono-mp3`, default
+    #[default]
+    Audio24Khz48KBitRateMonoMp3,
+    /// `audio-24khz-96kbitrate-mono-mp3`
+    Audio24Khz96KBitRateMonoMp3,
+    /// `audio-24khz-160kbitrate-mono-mp3`
+    Audio24Khz160KBitRateMonoMp3,
+    /// `audio-48khz-96kbitrate-mono-mp3`
+    Audio48Khz96KBitRateMonoMp3,
+    /// `audio-48khz-192kbitrate-mono-mp3`
+    Audio48Khz192KBitRateMonoMp3,
+    /// `ogg-16khz-16bit-mono-opus`
+    Ogg16Khz16BitMonoOpus,
+    /// `ogg-24khz-16bit-mono-opus`
+    Ogg24Khz16BitMonoOpus,
+    /// `ogg-48khz-16bit-mono-opus`
+    Ogg48Khz16BitMonoOpus,
+    /// `raw-8khz-8bit-mono-alaw`
+    Raw8Khz8BitMonoALaw,
+    /// `raw-8khz-8bit-mono-mulaw`
+    Raw8Khz8BitMonoMULaw,
+    /// `raw-8khz-16bit-mono-pcm`
+    Raw8Khz16BitMonoPcm,
+    /// `raw-16khz-16bit-mono-pcm`
+    Raw16Khz16BitMonoPcm,
+    /// `raw-16khz-16bit-mono-truesilk`
+    Raw16Khz16BitMonoTrueSilk,
+    /// `raw-22050hz-16bit-mono-pcm`
+    Raw22050Hz16BitMonoPcm,
+    /// `raw-24khz-16bit-mono-pcm`
+    Raw24Khz16BitMonoPcm,
+    /// `raw-24khz-16bit-mono-truesilk`
+    Raw24Khz16BitMonoTrueSilk,
+    /// `raw-44100hz-16bit-mono-pcm`
+    Raw44100Hz16BitMonoPcm,
+    /// `raw-48khz-16bit-mono-pcm`
+    Raw48Khz16BitMonoPcm,
+    /// `webm-16khz-16bit-mono-opus`
+    Webm16Khz16BitMonoOpus,
+    /// `webm-24khz-16bit-24kbps-mono-opus`
+    Webm24Khz16Bit24KbpsMonoOpus,
+    /// `webm-24khz-16bit-mono-opus`
+    Webm24Khz16BitMonoOpus,
+    /// `riff-8khz-8bit-mono-alaw`
+    Riff8Khz8BitMonoALaw,
+    /// `riff-8khz-8bit-mono-mulaw`
+    Riff8Khz8BitMonoMULaw,
+    /// `riff-8khz-16bit-mono-pcm`
+    Riff8Khz16BitMonoPcm,
+    /// `riff-22050hz-16bit-mono-pcm`
+    Riff22050Hz16BitMonoPcm,
+    /// `riff-24khz-16bit-mono-pcm`
+    Riff24Khz16BitMonoPcm,
+    /// `riff-44100hz-16bit-mono-pcm`
+    Riff44100Hz16BitMonoPcm,
+    /// `riff-48khz-16bit-mono-pcm`
+    Riff48Khz16BitMonoPcm,
+    /// Any other format string, sent to the service as it is, e.g. a format newer than this crate.
     ///
-    /// Streaming audio output formats:
-    /// + amr-wb-16000hz
-    /// + audio-16khz-16bit-32kbps-mono-opus
-    /// + audio-16khz-32kbitrate-mono-mp3
-    /// + audio-16khz-64kbitrate-mono-mp3
-    /// + audio-16khz-128kbitrate-mono-mp3
-    /// + audio-24khz-16bit-24kbps-mono-opus
-    /// + audio-24khz-16bit-48kbps-mono-opus
-    /// + audio-24khz-48kbitrate-mono-mp3
-    /// + audio-24khz-96kbitrate-mono-mp3
-    /// + audio-24khz-160kbitrate-mono-mp3
-    /// + audio-48khz-96kbitrate-mono-mp3
-    /// + audio-48khz-192kbitrate-mono-mp3
-    /// + ogg-16khz-16bit-mono-opus
-    /// + ogg-24khz-16bit-mono-opus
-    /// + ogg-48khz-16bit-mono-opus
-    /// + raw-8khz-8bit-mono-alaw
-    /// + raw-8khz-8bit-mono-mulaw
-    /// + raw-8khz-16bit-mono-pcm
-    /// + raw-16khz-16bit-mono-pcm
-    /// + raw-16khz-16bit-mono-truesilk
-    /// + raw-22050hz-16bit-mono-pcm
-    /// + raw-24khz-16bit-mono-pcm
-    /// + raw-24khz-16bit-mono-truesilk
-    /// + raw-44100hz-16bit-mono-pcm
-    /// + raw-48khz-16bit-mono-pcm
-    /// + webm-16khz-16bit-mono-opus
-    /// + webm-24khz-16bit-24kbps-mono-opus
-    /// + webm-24khz-16bit-mono-opus
+    /// Only rejected by [SpeechConfig::validate] when it looks like a typo of a known format.
+    Custom(String),
+}
+
+impl AudioOutputFormat {
+    /// Streaming audio output formats
+    pub const STREAMING: &'static [AudioOutputFormat] = &[
+        AudioOutputFormat::AmrWb16000Hz,
+        AudioOutputFormat::Audio16Khz16Bit32KbpsMonoOpus,
+        AudioOutputFormat::Audio16Khz32KBitRateMonoMp3,
+        AudioOutputFormat::Audio16Khz64KBitRateMonoMp3,
+        AudioOutputFormat::Audio16Khz128KBitRateMonoMp3,
+        AudioOutputFormat::Audio24Khz16Bit24KbpsMonoOpus,
+        AudioOutputFormat::Audio24Khz16Bit48KbpsMonoOpus,
+        AudioOutputFormat::Audio24Khz48KBitRateMonoMp3,
+        AudioOutputFormat::Audio24Khz96KBitRateMonoMp3,
+        AudioOutputFormat::Audio24Khz160KBitRateMonoMp3,
+        AudioOutputFormat::Audio48Khz96KBitRateMonoMp3,
+        AudioOutputFormat::Audio48Khz192KBitRateMonoMp3,
+        AudioOutputFormat::Ogg16Khz16BitMonoOpus,
+        AudioOutputFormat::Ogg24Khz16BitMonoOpus,
+        AudioOutputFormat::Ogg48Khz16BitMonoOpus,
+        AudioOutputFormat::Raw8Khz8BitMonoALaw,
+        AudioOutputFormat::Raw8Khz8BitMonoMULaw,
+        AudioOutputFormat::Raw8Khz16BitMonoPcm,
+        AudioOutputFormat::Raw16Khz16BitMonoPcm,
+        AudioOutputFormat::Raw16Khz16BitMonoTrueSilk,
+        AudioOutputFormat::Raw22050Hz16BitMonoPcm,
+        AudioOutputFormat::Raw24Khz16BitMonoPcm,
+        AudioOutputFormat::Raw24Khz16BitMonoTrueSilk,
+        AudioOutputFormat::Raw44100Hz16BitMonoPcm,
+        AudioOutputFormat::Raw48Khz16BitMonoPcm,
+        AudioOutputFormat::Webm16Khz16BitMonoOpus,
+        AudioOutputFormat::Webm24Khz16Bit24KbpsMonoOpus,
+        AudioOutputFormat::Webm24Khz16BitMonoOpus,
+    ];
+
+    /// NonStreaming audio output formats
+    pub const NON_STREAMING: &'static [AudioOutputFormat] = &[
+        AudioOutputFormat::Riff8Khz8BitMonoALaw,
+        AudioOutputFormat::Riff8Khz8BitMonoMULaw,
+        AudioOutputFormat::Riff8Khz16BitMonoPcm,
+        AudioOutputFormat::Riff22050Hz16BitMonoPcm,
+        AudioOutputFormat::Riff24Khz16BitMonoPcm,
+        AudioOutputFormat::Riff44100Hz16BitMonoPcm,
+        AudioOutputFormat::Riff48Khz16BitMonoPcm,
+    ];
+
+    /// Format string sent in `speech.config`
+    pub fn as_str(&self) -> &str {
+        match self {
+            AudioOutputFormat::AmrWb16000Hz => "amr-wb-16000hz",
+            AudioOutputFormat::Audio16Khz16Bit32KbpsMonoOpus => {
+                "audio-16khz-16bit-32kbps-mono-opus"
+            }
+            AudioOutputFormat::Audio16Khz32KBitRateMonoMp3 => "audio-16khz-32kbitrate-mono-mp3",
+            AudioOutputFormat::Audio16Khz64KBitRateMonoMp3 => "audio-16khz-64kbitrate-mono-mp3",
+            AudioOutputFormat::Audio16Khz128KBitRateMonoMp3 => "audio-16khz-128kbitrate-mono-mp3",
+            AudioOutputFormat::Audio24Khz16Bit24KbpsMonoOpus => {
+                "audio-24khz-16bit-24kbps-mono-opus"
+            }
+            AudioOutputFormat::Audio24Khz16Bit48KbpsMonoOpus => {
+                "audio-24khz-16bit-48kbps-mono-opus"
+            }
+            AudioOutputFormat::Audio24Khz48KBitRateMonoMp3 => "audio-24khz-48kbitrate-mono-mp3",
+            AudioOutputFormat::Audio24Khz96KBitRateMonoMp3 => "audio-24khz-96kbitrate-mono-mp3",
+            AudioOutputFormat::Audio24Khz160KBitRateMonoMp3 => "audio-24khz-160kbitrate-mono-mp3",
+            AudioOutputFormat::Audio48Khz96KBitRateMonoMp3 => "audio-48khz-96kbitrate-mono-mp3",
+            AudioOutputFormat::Audio48Khz192KBitRateMonoMp3 => "audio-48khz-192kbitrate-mono-mp3",
+            AudioOutputFormat::Ogg16Khz16BitMonoOpus => "ogg-16khz-16bit-mono-opus",
+            AudioOutputFormat::Ogg24Khz16BitMonoOpus => "ogg-24khz-16bit-mono-opus",
+            AudioOutputFormat::Ogg48Khz16BitMonoOpus => "ogg-48khz-16bit-mono-opus",
+            AudioOutputFormat::Raw8Khz8BitMonoALaw => "raw-8khz-8bit-mono-alaw",
+            AudioOutputFormat::Raw8Khz8BitMonoMULaw => "raw-8khz-8bit-mono-mulaw",
+            AudioOutputFormat::Raw8Khz16BitMonoPcm => "raw-8khz-16bit-mono-pcm",
+            AudioOutputFormat::Raw16Khz16BitMonoPcm => "raw-16khz-16bit-mono-pcm",
+            AudioOutputFormat::Raw16Khz16BitMonoTrueSilk => "raw-16khz-16bit-mono-truesilk",
+            AudioOutputFormat::Raw22050Hz16BitMonoPcm => "raw-22050hz-16bit-mono-pcm",
+            AudioOutputFormat::Raw24Khz16BitMonoPcm => "raw-24khz-16bit-mono-pcm",
+            AudioOutputFormat::Raw24Khz16BitMonoTrueSilk => "raw-24khz-16bit-mono-truesilk",
+            AudioOutputFormat::Raw44100Hz16BitMonoPcm => "raw-44100hz-16bit-mono-pcm",
+            AudioOutputFormat::Raw48Khz16BitMonoPcm => "raw-48khz-16bit-mono-pcm",
+            AudioOutputFormat::Webm16Khz16BitMonoOpus => "webm-16khz-16bit-mono-opus",
+            AudioOutputFormat::Webm24Khz16Bit24KbpsMonoOpus => "webm-24khz-16bit-24kbps-mono-opus",
+            AudioOutputFormat::Webm24Khz16BitMonoOpus => "webm-24khz-16bit-mono-opus",
+            AudioOutputFormat::Riff8Khz8BitMonoALaw => "riff-8khz-8bit-mono-alaw",
+            AudioOutputFormat::Riff8Khz8BitMonoMULaw => "riff-8khz-8bit-mono-mulaw",
+            AudioOutputFormat::Riff8Khz16BitMonoPcm => "riff-8khz-16bit-mono-pcm",
+            AudioOutputFormat::Riff22050Hz16BitMonoPcm => "riff-22050hz-16bit-mono-pcm",
+            AudioOutputFormat::Riff24Khz16BitMonoPcm => "riff-24khz-16bit-mono-pcm",
+            AudioOutputFormat::Riff44100Hz16BitMonoPcm => "riff-44100hz-16bit-mono-pcm",
+            AudioOutputFormat::Riff48Khz16BitMonoPcm => "riff-48khz-16bit-mono-pcm",
+            AudioOutputFormat::Custom(format) => format,
+        }
+    }
+
+    /// Return [Error::InvalidAudioFormat] with the closest known formats if this is a [Custom](Self::Custom) format
+    /// which looks like a typo of a known one, e.g. `Audio-24khz-48kbitrate-mono-mp3` or `audio-24khz-48kbitrate-mono-mpe`.
     ///
-    /// NonStreaming audio output formats:
-    /// + riff-8khz-8bit-mono-alaw
-    /// + riff-8khz-8bit-mono-mulaw
-    /// + riff-8khz-16bit-mono-pcm
-    /// + riff-22050hz-16bit-mono-pcm
-    /// + riff-24khz-16bit-mono-pcm
-    /// + riff-44100hz-16bit-mono-pcm
-    /// + riff-48khz-16bit-mono-pcm
-    pub audio_format: String,
-    pub pitch: i32,
-    pub rate: i32,
-    pub volume: i32,
+    /// Other custom formats pass, formats differing from a known one only in their numbers,
+    /// like `audio-48khz-320kbitrate-mono-mp3`, are taken as new formats of the service.
+    pub fn validate(&self) -> Result<()> {
+        let format = match self {
+            AudioOutputFormat::Custom(format) => format,
+            _ => return Ok(()),
+        };
+        let mut known = Self::STREAMING.iter().chain(Self::NON_STREAMING);
+        if known.clone().any(|known| known.as_str() == format) {
+            return Ok(());
+        }
+        let shape = number_shape(format);
+        let near_miss = known.any(|known| {
+            let known = known.as_str();
+            known.eq_ignore_ascii_case(format)
+                || (1..=2).contains(&edit_distance(&shape, &number_shape(known)))
+        });
+        if !near_miss {
+            return Ok(());
+        }
+        let mut candidates: Vec<(usize, &str)> = Self::STREAMING
+            .iter()
+            .chain(Self::NON_STREAMING)
+            .map(|known| (edit_distance(format, known.as_str()), known.as_str()))
+            .filter(|(distance, known)| *distance <= known.len() / 3)
+            .collect();
+        candidates.sort();
+        Err(Error::InvalidAudioFormat {
+            format: format.clone(),
+            suggestions: candidates
+                .into_iter()
+                .take(3)
+                .map(|(_, known)| known.to_owned())
+                .collect(),
+        })
+    }
+
+    /// Whether this is a [Custom](Self::Custom) format
+    pub fn is_custom(&self) -> bool {
+        matches!(self, AudioOutputFormat::Custom(_))
+    }
+}
+
+/// `format` with each run of digits replaced by `#`, so formats only differing in numbers have the same shape
+fn number_shape(format: &str) -> String {
+    let mut shape = String::with_capacity(format.len());
+    for c in format.chars() {
+        if !c.is_ascii_digit() {
+            shape.push(c);
+        } else if !shape.ends_with('#') {
+            shape.push('#');
+        }
+    }
+    shape
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                previous
+            } else {
+                previous.min(row[j]).min(current) + 1
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+impl std::fmt::Display for AudioOutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for AudioOutputFormat {
+    type Err = std::convert::Infallible;
+
+    /// Unknown format strings become [Custom](AudioOutputFormat::Custom)
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(s.into())
+    }
+}
+
+impl From<&str> for AudioOutputFormat {
+    fn from(format: &str) -> Self {
+        Self::STREAMING
+            .iter()
+            .chain(Self::NON_STREAMING)
+            .find(|known| known.as_str() == format)
+            .cloned()
+            .unwrap_or_else(|| AudioOutputFormat::Custom(format.to_owned()))
+    }
+}
+
+impl From<String> for AudioOutputFormat {
+    fn from(format: String) -> Self {
+        format.as_str().into()
+    }
+}
+
+impl PartialEq<str> for AudioOutputFormat {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for AudioOutputFormat {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for AudioOutputFormat {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl From<AudioOutputFormat> for String {
+    fn from(format: AudioOutputFormat) -> Self {
+        match format {
+            AudioOutputFormat::Custom(format) => format,
+            format => format.as_str().to_owned(),
+        }
+    }
+}
+
+/// Audio Container used with [Quality] to select an audio output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AudioContainer {
+    Mp3,
+    /// Opus in Ogg
+    Ogg,
+    /// Opus in WebM
+    Webm,
+    /// PCM in WAV (RIFF), NonStreaming
+    Riff,
+    /// Headerless PCM
+    Raw,
+}
+
+/// Audio Quality Tier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Quality {
+    /// 8khz - 16khz, smallest size
+    Telephony,
+    /// 24khz, default of most voices
+    Standard,
+    /// 48khz, or the highest the container offers
+    High,
+}
+
+impl Quality {
+    /// Get the audio output format of this quality in `container`
+    pub fn audio_format(&self, container: AudioContainer) -> AudioOutputFormat {
+        match (container, self) {
+            (AudioContainer::Mp3, Quality::Telephony) => {
+                AudioOutputFormat::Audio16Khz32KBitRateMonoMp3
+            }
+            (AudioContainer::Mp3, Quality::Standard) => {
+                AudioOutputFormat::Audio24Khz48KBitRateMonoMp3
+            }
+            (AudioContainer::Mp3, Quality::High) => AudioOutputFormat::Audio48Khz192KBitRateMonoMp3,
+            (AudioContainer::Ogg, Quality::Telephony) => AudioOutputFormat::Ogg16Khz16BitMonoOpus,
+            (AudioContainer::Ogg, Quality::Standard) => AudioOutputFormat::Ogg24Khz16BitMonoOpus,
+            (AudioContainer::Ogg, Quality::High) => AudioOutputFormat::Ogg48Khz16BitMonoOpus,
+            (AudioContainer::Webm, Quality::Telephony) => AudioOutputFormat::Webm16Khz16BitMonoOpus,
+            (AudioContainer::Webm, Quality::Standard | Quality::High) => {
+                AudioOutputFormat::Webm24Khz16BitMonoOpus
+            }
+            (AudioContainer::Riff, Quality::Telephony) => AudioOutputFormat::Riff8Khz16BitMonoPcm,
+            (AudioContainer::Riff, Quality::Standard) => AudioOutputFormat::Riff24Khz16BitMonoPcm,
+            (AudioContainer::Riff, Quality::High) => AudioOutputFormat::Riff48Khz16BitMonoPcm,
+            (AudioContainer::Raw, Quality::Telephony) => AudioOutputFormat::Raw8Khz16BitMonoPcm,
+            (AudioContainer::Raw, Quality::Standard) => AudioOutputFormat::Raw24Khz16BitMonoPcm,
+            (AudioContainer::Raw, Quality::High) => AudioOutputFormat::Raw48Khz16BitMonoPcm,
+        }
+    }
 }
 
 impl From<&super::voice::Voice> for SpeechConfig {
     fn from(voice: &super::voice::Voice) -> Self {
         let audio_output_format = if let Some(ref output_format) = voice.suggested_codec {
-            output_format.clone()
+            output_format.as_str().into()
         } else {
-            "audio-24khz-48kbitrate-mono-mp3".to_string()
+            AudioOutputFormat::default()
         };
         Self {
             voice_name: voice.name.clone(),
             audio_format: audio_output_format,
-            pitch: 0,
-            rate: 0,
-            volume: 0,
+            pitch: Pitch::default(),
+            rate: Rate::default(),
+            volume: Volume::default(),
+            audio_duration: None,
+            lexicon_uris: Vec::new(),
+            style: None,
+            style_degree: None,
+            role: None,
+            phonemes: std::collections::HashMap::new(),
+            lang: voice.locale.clone().unwrap_or_else(default_lang),
+            metadata_options: MetadataOptions::default(),
+            silences: Vec::new(),
+            speech_context: None,
+            max_ssml_bytes: None,
+            request_id: None,
         }
     }
 }
 
+/// Valid range of [Pitch::Hz]
+pub const PITCH_RANGE: std::ops::RangeInclusive<i32> = -100..=100;
+/// Valid range of [Pitch::Semitones], one octave down or up
+pub const PITCH_SEMITONES_RANGE: std::ops::RangeInclusive<i32> = -12..=12;
+/// Valid range of [Pitch::Percent]
+pub const PITCH_PERCENT_RANGE: std::ops::RangeInclusive<i32> = -50..=50;
+/// Valid range of [SpeechConfig::rate] in percent, 0.5x to 2x speed
+pub const RATE_RANGE: std::ops::RangeInclusive<i32> = -50..=100;
+/// Valid range of [SpeechConfig::volume] in percent
+pub const VOLUME_RANGE: std::ops::RangeInclusive<i32> = -100..=100;
+/// Valid range of [Volume::Decibels]
+pub const VOLUME_DECIBELS_RANGE: std::ops::RangeInclusive<i32> = -60..=20;
+/// Valid range of [SpeechConfig::style_degree]
+pub const STYLE_DEGREE_RANGE: std::ops::RangeInclusive<f32> = 0.01..=2.0;
+/// Valid values of [SpeechConfig::role]
+pub const SPEAKING_ROLES: &[&str] = &[
+    "Girl",
+    "Boy",
+    "YoungAdultFemale",
+    "YoungAdultMale",
+    "OlderAdultFemale",
+    "OlderAdultMale",
+    "SeniorFemale",
+    "SeniorMale",
+];
+
+impl SpeechConfig {
+    /// Create a config with a bundled default neural voice of `locale`, e.g. `de-DE` or `de`,
+    /// without fetching the voice list. Return `None` if the locale has no bundled voice.
+    ///
+    /// A language only locale, or a region without a bundled voice, falls back to the first voice of the language.
+    pub fn default_for_locale(locale: &str) -> Option<Self> {
+        let language = locale.split(['-', '_']).next()?;
+        let (locale, voice) = crate::constants::DEFAULT_VOICES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(&locale.replace('_', "-")))
+            .or_else(|| {
+                crate::constants::DEFAULT_VOICES.iter().find(|(known, _)| {
+                    known
+                        .split('-')
+                        .next()
+                        .is_some_and(|known| known.eq_ignore_ascii_case(language))
+                })
+            })?;
+        Some(Self::with_voice_short_name(&format!(
+            "{}-{}",
+            locale, voice
+        )))
+    }
+
+    /// Create a config from a voice short name like `en-US-AriaNeural`, without fetching the voice list.
+    ///
+    /// Other fields are the same as converting a [Voice](crate::voice::Voice), chain `with_*` helpers to change them.
+    pub fn with_voice_short_name(short_name: &str) -> Self {
+        let name = match short_name.rsplit_once('-') {
+            Some((locale, voice)) => format!(
+                "Microsoft Server Speech Text to Speech Voice ({}, {})",
+                locale, voice
+            ),
+            None => short_name.to_owned(),
+        };
+        let mut voice = crate::voice::Voice::from(name);
+        voice.short_name = Some(short_name.to_owned());
+        voice.locale = locale_from_voice_name(short_name);
+        Self::from(&voice)
+    }
+
+    /// Set voice name
+    pub fn with_voice_name(mut self, voice_name: impl Into<String>) -> Self {
+        self.voice_name = voice_name.into();
+        self
+    }
+
+    /// Set audio output format
+    pub fn with_format(mut self, audio_format: impl Into<AudioOutputFormat>) -> Self {
+        self.audio_format = audio_format.into();
+        self
+    }
+
+    /// Set pitch, a number is relative Hz
+    pub fn with_pitch(mut self, pitch: impl Into<Pitch>) -> Self {
+        self.pitch = pitch.into();
+        self
+    }
+
+    /// Set rate, a number is relative percent
+    pub fn with_rate(mut self, rate: impl Into<Rate>) -> Self {
+        self.rate = rate.into();
+        self
+    }
+
+    /// Set volume, a number is relative percent
+    pub fn with_volume(mut self, volume: impl Into<Volume>) -> Self {
+        self.volume = volume.into();
+        self
+    }
+
+    /// Set speaking style
+    pub fn with_style(mut self, style: impl Into<String>) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    /// Set `xml:lang`
+    pub fn with_lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = lang.into();
+        self
+    }
+
+    /// Create a [SpeechConfigBuilder]
+    pub fn builder() -> SpeechConfigBuilder {
+        SpeechConfigBuilder::default()
+    }
+}
+
+/// [SpeechConfig] Builder
+///
+/// [build](Self::build) validates the config and returns [Error::InvalidSpeechConfig] on bad values,
+/// so misconfiguration is caught before opening a websocket.
+#[derive(Debug, Clone, Default)]
+pub struct SpeechConfigBuilder {
+    voice_name: Option<String>,
+    audio_format: Option<AudioOutputFormat>,
+    pitch: Pitch,
+    rate: Rate,
+    volume: Volume,
+    audio_duration: Option<std::time::Duration>,
+    lexicon_uris: Vec<String>,
+    style: Option<String>,
+    style_degree: Option<f32>,
+    role: Option<String>,
+    phonemes: std::collections::HashMap<String, String>,
+    lang: Option<String>,
+    metadata_options: MetadataOptions,
+    silences: Vec<Silence>,
+}
+
+impl SpeechConfigBuilder {
+    /// Create a builder, same as [SpeechConfig::builder]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set voice name, required
+    pub fn voice_name(mut self, voice_name: impl Into<String>) -> Self {
+        self.voice_name = Some(voice_name.into());
+        self
+    }
+
+    /// Set audio output format, default `audio-24khz-48kbitrate-mono-mp3`
+    ///
+    /// [Custom](AudioOutputFormat::Custom) formats which look like a typo of a known one are rejected by [build](Self::build), see [SpeechConfig::validate].
+    pub fn audio_format(mut self, audio_format: impl Into<AudioOutputFormat>) -> Self {
+        self.audio_format = Some(audio_format.into());
+        self
+    }
+
+    /// Set pitch, relative Hz must be in [PITCH_RANGE]
+    pub fn pitch(mut self, pitch: impl Into<Pitch>) -> Self {
+        self.pitch = pitch.into();
+        self
+    }
+
+    /// Set rate, relative percent must be in [RATE_RANGE]
+    pub fn rate(mut self, rate: impl Into<Rate>) -> Self {
+        self.rate = rate.into();
+        self
+    }
+
+    /// Set volume, relative percent must be in [VOLUME_RANGE]
+    pub fn volume(mut self, volume: impl Into<Volume>) -> Self {
+        self.volume = volume.into();
+        self
+    }
+
+    /// Set target audio duration
+    pub fn audio_duration(mut self, audio_duration: std::time::Duration) -> Self {
+        self.audio_duration = Some(audio_duration);
+        self
+    }
+
+    /// Add an external pronunciation lexicon URI, e.g. a shared `.xml` PLS file served over https
+    pub fn lexicon_uri(mut self, uri: impl Into<String>) -> Self {
+        self.lexicon_uris.push(uri.into());
+        self
+    }
+
+    /// Set speaking style
+    pub fn style(mut self, style: impl Into<String>) -> Self {
+        self.style = Some(style.into());
+        self
+    }
+
+    /// Set style intensity, must be in [STYLE_DEGREE_RANGE] and requires a [style](Self::style)
+    pub fn style_degree(mut self, style_degree: f32) -> Self {
+        self.style_degree = Some(style_degree);
+        self
+    }
+
+    /// Set role-play, must be one of [SPEAKING_ROLES]
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    /// Set `xml:lang`, default is the locale of the voice name or `en-US`
+    pub fn lang(mut self, lang: impl Into<String>) -> Self {
+        self.lang = Some(lang.into());
+        self
+    }
+
+    /// Add a silence, at most [MAX_SILENCE]
+    pub fn silence(mut self, silence_type: SilenceType, duration: std::time::Duration) -> Self {
+        self.silences.push(Silence {
+            silence_type,
+            duration,
+        });
+        self
+    }
+
+    /// Enable or disable `SentenceBoundary` metadata, default disabled
+    pub fn sentence_boundary(mut self, enabled: bool) -> Self {
+        self.metadata_options.sentence_boundary_enabled = enabled;
+        self
+    }
+
+    /// Enable or disable `WordBoundary` metadata, default enabled
+    pub fn word_boundary(mut self, enabled: bool) -> Self {
+        self.metadata_options.word_boundary_enabled = enabled;
+        self
+    }
+
+    /// Add an IPA pronunciation override of `word`
+    pub fn phoneme(mut self, word: impl Into<String>, ph: impl Into<String>) -> Self {
+        self.phonemes.insert(word.into(), ph.into());
+        self
+    }
+
+    /// Validate and build [SpeechConfig]
+    pub fn build(self) -> Result<SpeechConfig> {
+        let voice_name = self.voice_name.unwrap_or_default();
+        let lang = match self.lang {
+            Some(lang) => lang,
+            None => locale_from_voice_name(&voice_name).unwrap_or_else(default_lang),
+        };
+        let config = SpeechConfig {
+            voice_name,
+            audio_format: self.audio_format.unwrap_or_default(),
+            pitch: self.pitch,
+            rate: self.rate,
+            volume: self.volume,
+            audio_duration: self.audio_duration,
+            lexicon_uris: self.lexicon_uris,
+            style: self.style,
+            style_degree: self.style_degree,
+            role: self.role,
+            phonemes: self.phonemes,
+            lang,
+            metadata_options: self.metadata_options,
+            silences: self.silences,
+            speech_context: None,
+            max_ssml_bytes: None,
+            request_id: None,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+impl SpeechConfig {
+    /// Check voice name, audio format, prosody ranges, style and role.
+    ///
+    /// Called by [SpeechConfigBuilder::build], call it to check a config built by hand.
+    /// Return [Error::InvalidAudioFormat] for [Custom](AudioOutputFormat::Custom) formats which look like a typo of a known one,
+    /// and [Error::InvalidSpeechConfig] for other fields.
+    pub fn validate(&self) -> Result<()> {
+        if self.voice_name.trim().is_empty() {
+            return Err(invalid_config("voice_name", "voice name is empty"));
+        }
+        check_voice_name(&self.voice_name)?;
+
+        self.audio_format.validate()?;
+
+        if let Some(ref style) = self.style {
+            if style.is_empty() || !style.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+                return Err(invalid_config(
+                    "style",
+                    format!("invalid style name: {}", style),
+                ));
+            }
+        }
+        for uri in &self.lexicon_uris {
+            if !(uri.starts_with("https://") || uri.starts_with("http://")) {
+                return Err(invalid_config(
+                    "lexicon_uris",
+                    format!("lexicon uri is not a http(s) url: {}", uri),
+                ));
+            }
+        }
+        if let Some(style_degree) = self.style_degree {
+            if self.style.is_none() {
+                return Err(invalid_config(
+                    "style_degree",
+                    "style degree requires a style",
+                ));
+            }
+            if !STYLE_DEGREE_RANGE.contains(&style_degree) {
+                return Err(invalid_config(
+                    "style_degree",
+                    format!("{} is out of range {:?}", style_degree, STYLE_DEGREE_RANGE),
+                ));
+            }
+        }
+        if let Some(ref role) = self.role {
+            if !SPEAKING_ROLES.contains(&role.as_str()) {
+                return Err(invalid_config("role", format!("unknown role: {}", role)));
+            }
+        }
+        for silence in &self.silences {
+            if silence.duration > MAX_SILENCE {
+                return Err(invalid_config(
+                    "silences",
+                    format!(
+                        "{} silence of {:?} is longer than {:?}",
+                        silence.silence_type.as_str(),
+                        silence.duration,
+                        MAX_SILENCE
+                    ),
+                ));
+            }
+        }
+        self.pitch.validate()?;
+        self.rate.validate()?;
+        self.volume.validate()?;
+        Ok(())
+    }
+}
+
+fn check_voice_name(voice_name: &str) -> Result<()> {
+    if voice_name.contains(['<', '>', '&', '\'', '"']) {
+        return Err(invalid_config(
+            "voice_name",
+            format!("voice name contains xml special characters: {}", voice_name),
+        ));
+    }
+    Ok(())
+}
+
+fn invalid_config(field: &'static str, reason: impl Into<String>) -> Error {
+    Error::InvalidSpeechConfig {
+        field,
+        reason: reason.into(),
+    }
+}
+
+/// Sample encoding of a `raw-*` audio output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RawEncoding {
+    /// signed 16-bit little-endian PCM
+    Pcm16,
+    ALaw,
+    MuLaw,
+}
+
+/// Parse a `raw-*` audio output format into its encoding and sample rate.
+///
+/// Return `None` for container formats and formats without a standard raw encoding (truesilk).
+pub(crate) fn parse_raw_audio_format(audio_format: &str) -> Option<(RawEncoding, u32)> {
+    let parts: Vec<&str> = audio_format.split('-').collect();
+    if parts.len() != 5 || parts[0] != "raw" {
+        return None;
+    }
+    let sample_rate = if let Some(khz) = parts[1].strip_suffix("khz") {
+        khz.parse::<u32>().ok()? * 1000
+    } else {
+        parts[1].strip_suffix("hz")?.parse().ok()?
+    };
+    let encoding = match (parts[2], parts[4]) {
+        ("16bit", "pcm") => RawEncoding::Pcm16,
+        ("8bit", "alaw") => RawEncoding::ALaw,
+        ("8bit", "mulaw") => RawEncoding::MuLaw,
+        _ => return None,
+    };
+    Some((encoding, sample_rate))
+}
+
 /// Audio Metadata
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
 pub struct AudioMetadata {
     pub metadata_type: Option<String>,
     pub offset: u64,
@@ -88,37 +996,84 @@ pub struct AudioMetadata {
     pub text: Option<String>,
     pub length: u64,
     pub boundary_type: Option<String>,
+    /// mark name of a [Bookmark](Self::BOOKMARK_TYPE) entry, from `<bookmark mark='...'/>`
+    #[serde(default)]
+    pub bookmark: Option<String>,
 }
 
 impl AudioMetadata {
-    fn from_str(text: &str) -> Result<Vec<Self>> {
-        let value: serde_json::Value = serde_json::from_str(text)?;
-        if let Some(items) = value["Metadata"].as_array() {
-            let mut audio_metadata = Vec::new();
-            for item in items {
-                let metadata_type = item["Type"].as_str().map(|x| x.to_owned());
-                let offset = item["Data"]["Offset"].as_u64().unwrap_or(0);
-                let duration = item["Data"]["Duration"].as_u64().unwrap_or(0);
-                let text = item["Data"]["text"]["Text"].as_str().map(|x| x.to_owned());
-                let length = item["Data"]["text"]["Length"].as_u64().unwrap_or(0);
-                let boundary_type = item["Data"]["text"]["BoundaryType"]
-                    .as_str()
-                    .map(|x| x.to_owned());
-                audio_metadata.push(AudioMetadata {
-                    metadata_type,
-                    offset,
-                    duration,
-                    text,
-                    length,
-                    boundary_type,
-                });
+    /// Audio offset where this boundary starts. `offset` is in 100-nanosecond ticks.
+    pub fn start(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos(self.offset * 100)
+    }
+
+    /// Audio offset where this boundary ends. `offset` and `duration` are in 100-nanosecond ticks.
+    pub fn end(&self) -> std::time::Duration {
+        std::time::Duration::from_nanos((self.offset + self.duration) * 100)
+    }
+
+    /// Parse `audio.metadata` json body.
+    ///
+    /// Malformed json or items which are not `{"Type": "..", "Data": {..}}` objects
+    /// are returned as one [Unknown](Self::UNKNOWN_TYPE) entry instead of failing the whole turn.
+    fn from_str(text: &str) -> Vec<Self> {
+        let value: serde_json::Value = match serde_json::from_str(text) {
+            Ok(value) => value,
+            Err(_) => return vec![Self::unknown()],
+        };
+        let items = match value["Metadata"].as_array() {
+            Some(items) => items,
+            None => return vec![Self::unknown()],
+        };
+
+        let mut audio_metadata = Vec::new();
+        for item in items {
+            if !item["Type"].is_string() || !item["Data"].is_object() {
+                audio_metadata.push(Self::unknown());
+                continue;
             }
-            Ok(audio_metadata)
-        } else {
-            Err(Error::UnexpectedMessage(format!(
-                "unexpected json text: {}",
-                text
-            )))
+            let metadata_type = item["Type"].as_str().map(|x| x.to_owned());
+            let offset = item["Data"]["Offset"].as_u64().unwrap_or(0);
+            let duration = item["Data"]["Duration"].as_u64().unwrap_or(0);
+            let text = item["Data"]["text"]["Text"].as_str().map(|x| x.to_owned());
+            let length = item["Data"]["text"]["Length"].as_u64().unwrap_or(0);
+            let boundary_type = item["Data"]["text"]["BoundaryType"]
+                .as_str()
+                .map(|x| x.to_owned());
+            let bookmark = item["Data"]["Bookmark"].as_str().map(|x| x.to_owned());
+            audio_metadata.push(AudioMetadata {
+                metadata_type,
+                offset,
+                duration,
+                text,
+                length,
+                boundary_type,
+                bookmark,
+            });
+        }
+        audio_metadata
+    }
+
+    /// `metadata_type` of entries which can not be parsed
+    pub const UNKNOWN_TYPE: &'static str = "Unknown";
+
+    /// `metadata_type` of entries reached by a `<bookmark>` element, see [bookmark](Self::bookmark)
+    pub const BOOKMARK_TYPE: &'static str = "Bookmark";
+
+    /// Whether this entry is a reached bookmark
+    pub fn is_bookmark(&self) -> bool {
+        self.metadata_type.as_deref() == Some(Self::BOOKMARK_TYPE)
+    }
+
+    fn unknown() -> Self {
+        AudioMetadata {
+            metadata_type: Some(Self::UNKNOWN_TYPE.to_owned()),
+            offset: 0,
+            duration: 0,
+            text: None,
+            length: 0,
+            boundary_type: None,
+            bookmark: None,
         }
     }
 }
@@ -128,29 +1083,52 @@ enum ProcessedMessage {
     AudioMetadata(Vec<AudioMetadata>),
 }
 
+/// State of a synthesis turn
+#[derive(Debug, Default)]
+struct TurnState {
+    turn_start: bool,
+    response: bool,
+    turn_end: bool,
+    /// the server closed the connection
+    closed: bool,
+    /// end of the last audio boundary in 100-nanosecond ticks
+    audio_end: u64,
+}
+
+impl TurnState {
+    /// `turn.start`, `response` and `turn.end` are all received
+    fn is_complete(&self) -> bool {
+        self.turn_start && self.response && self.turn_end
+    }
+}
+
 fn process_message(
     message: tungstenite::Message,
-    turn_start: &mut bool,
-    response: &mut bool,
-    turn_end: &mut bool,
+    state: &mut TurnState,
 ) -> Result<Option<ProcessedMessage>> {
+    let usage = usage::UsageTracker::global();
+    usage.add_bytes(message.len() as u64);
     match message {
         tungstenite::Message::Text(text) => {
             if text.contains("audio.metadata") {
                 if let Some(index) = text.find("\r\n\r\n") {
-                    let metadata = AudioMetadata::from_str(&text[index + 4..])?;
+                    let metadata = AudioMetadata::from_str(&text[index + 4..]);
+                    for item in &metadata {
+                        state.audio_end = state.audio_end.max(item.offset + item.duration);
+                    }
                     Ok(Some(ProcessedMessage::AudioMetadata(metadata)))
                 } else {
                     Ok(None)
                 }
             } else if text.contains("turn.start") {
-                *turn_start = true;
+                state.turn_start = true;
                 Ok(None)
             } else if text.contains("response") {
-                *response = true;
+                state.response = true;
                 Ok(None)
             } else if text.contains("turn.end") {
-                *turn_end = true;
+                state.turn_end = true;
+                usage.add_turn(state.audio_end);
                 Ok(None)
             } else {
                 Err(Error::UnexpectedMessage(format!(
@@ -160,17 +1138,25 @@ fn process_message(
             }
         }
         tungstenite::Message::Binary(bytes) => {
-            if *turn_start || *response {
+            // header length (2), header, audio bytes
+            if (state.turn_start || state.response) && bytes.len() >= 2 {
                 let header_len = u16::from_be_bytes([bytes[0], bytes[1]]) as usize;
+                // ignore truncated frames
+                if bytes.len() < header_len + 2 {
+                    return Ok(None);
+                }
                 Ok(Some(ProcessedMessage::AudioBytes((bytes, header_len + 2))))
             } else {
                 Ok(None)
             }
         }
         tungstenite::Message::Close(_) => {
-            *turn_end = true;
+            state.turn_end = true;
+            state.closed = true;
             Ok(None)
         }
+        // tungstenite queues the Pong reply to a Ping itself
+        tungstenite::Message::Ping(_) | tungstenite::Message::Pong(_) => Ok(None),
         _ => Err(Error::UnexpectedMessage(format!(
             "unexpected message: {}",
             message
@@ -178,16 +1164,26 @@ fn process_message(
     }
 }
 
+/// server time minus local time in seconds, from the last recorded server `Date` header
+static CLOCK_SKEW_SECONDS: std::sync::atomic::AtomicI64 = std::sync::atomic::AtomicI64::new(0);
+
+/// Record the clock skew to the server from a HTTP `Date` header, e.g. `Wed, 16 Oct 2024 08:00:00 GMT`
+pub(crate) fn record_server_date(date: &str) {
+    if let Ok(server_time) = chrono::DateTime::parse_from_rfc2822(date) {
+        let skew = server_time.timestamp() - chrono::Utc::now().timestamp();
+        CLOCK_SKEW_SECONDS.store(skew, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
 // try to fix china mainland 403 forbidden issue
 // solution from:
 // https://github.com/rany2/edge-tts/issues/290#issuecomment-2464956570
 fn gen_sec_ms_gec() -> String {
-    // UTC time from 1601-01-01
-    let duration = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap()
-        + std::time::Duration::from_secs(11644473600);
-    let ticks = duration.as_nanos() / 100;
+    // UTC time from 1601-01-01, corrected by the clock skew to the server
+    let seconds = chrono::Utc::now().timestamp()
+        + CLOCK_SKEW_SECONDS.load(std::sync::atomic::Ordering::Relaxed)
+        + 11644473600;
+    let ticks = seconds.max(0) as u128 * 10_000_000;
     let ticks = ticks - ticks % 3_000_000_000;
 
     let mut hasher = sha2::Sha256::new();
@@ -200,14 +1196,19 @@ fn gen_sec_ms_gec() -> String {
     hex_str
 }
 
-fn build_websocket_request() -> Result<tungstenite::handshake::client::Request> {
+fn build_websocket_request(
+    config: &connection::ConnectionConfig,
+) -> Result<tungstenite::handshake::client::Request> {
     use super::constants;
     use tungstenite::client::IntoClientRequest;
     use tungstenite::http::header;
 
     let uuid = uuid::Uuid::new_v4().simple().to_string();
     let sec_ms_gec = gen_sec_ms_gec();
-    let sec_ms_gec_version = "1-130.0.2849.68";
+    let sec_ms_gec_version = config
+        .sec_ms_gec_version
+        .as_deref()
+        .unwrap_or(constants::SEC_MS_GEC_VERSION);
     let mut request = format!(
         "{}{}&Sec-MS-GEC={}&Sec-MS-GEC-Version={}",
         constants::WSS_URL,
@@ -231,55 +1232,377 @@ fn build_websocket_request() -> Result<tungstenite::handshake::client::Request>
     );
     headers.insert(
         header::USER_AGENT,
-        constants::USER_AGENT
+        config
+            .user_agent
+            .as_deref()
+            .unwrap_or(constants::USER_AGENT)
             .parse()
             .map_err(|err| tungstenite::Error::from(http::Error::from(err)))?,
     );
     headers.insert(
         header::ORIGIN,
-        constants::ORIGIN
+        config
+            .origin
+            .as_deref()
+            .unwrap_or(constants::ORIGIN)
             .parse()
             .map_err(|err| tungstenite::Error::from(http::Error::from(err)))?,
     );
+    for (name, value) in &config.headers {
+        headers.insert(
+            header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|err| tungstenite::Error::from(http::Error::from(err)))?,
+            value
+                .parse()
+                .map_err(|err| tungstenite::Error::from(http::Error::from(err)))?,
+        );
+    }
     Ok(request)
 }
 
+/// `context` of the speech.config message of `config`
+fn speech_context(config: &SpeechConfig) -> context::SpeechContext {
+    match config.speech_context {
+        Some(ref speech_context) => speech_context.clone(),
+        None => context::SpeechContext::from(config),
+    }
+}
+
+/// `config` requesting `metadata_options`, also in its customized [speech_context](SpeechConfig::speech_context)
+fn with_metadata_options(config: &SpeechConfig, metadata_options: MetadataOptions) -> SpeechConfig {
+    let mut config = config.clone();
+    config.metadata_options = metadata_options;
+    if let Some(ref mut speech_context) = config.speech_context {
+        speech_context.synthesis.audio.metadata_options = metadata_options;
+    }
+    config
+}
+
 fn build_config_message(config: &SpeechConfig) -> tungstenite::Message {
-    static SPEECH_CONFIG_HEAD: &str = r#"{"context":{"synthesis":{"audio":{"metadataoptions":{"sentenceBoundaryEnabled":"false","wordBoundaryEnabled":"true"},"outputFormat":""#;
-    static SPEECH_CONFIG_TAIL: &str = r#""}}}}"#;
+    build_context_message(&speech_context(config))
+}
+
+fn build_context_message(speech_context: &context::SpeechContext) -> tungstenite::Message {
+    let speech_config = serde_json::json!({ "context": speech_context });
     let speech_config_message = format!(
-        "X-Timestamp:{}\r\nContent-Type:application/json; charset=utf-8\r\nPath:speech.config\r\n\r\n{}{}{}",
+        "X-Timestamp:{}\r\nContent-Type:application/json; charset=utf-8\r\nPath:speech.config\r\n\r\n{}",
         chrono::Local::now().to_rfc2822(),
-        SPEECH_CONFIG_HEAD,
-        config.audio_format,
-        SPEECH_CONFIG_TAIL
+        speech_config,
     );
     tungstenite::Message::Text(speech_config_message)
 }
 
-fn build_ssml_message(text: &str, config: &SpeechConfig) -> tungstenite::Message {
-    let ssml = format!(
-        "<speak version='1.0' xmlns='http://www.w3.org/2001/10/synthesis' xml:lang='en-US'><voice name='{}'><prosody pitch='{:+}Hz' rate='{:+}%' volume='{:+}%'>{}</prosody></voice></speak>",
-        config.voice_name,
-        config.pitch,
-        config.rate,
-        config.volume,
-        text,
+/// Text followed by an optional pause, see [Sender::send_segments](stream::Sender::send_segments)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSegment {
+    pub text: String,
+    /// pause after the text, emitted as `<break time='...ms'/>`, at most [MAX_BREAK_MS](crate::ssml::MAX_BREAK_MS)
+    pub pause_after: Option<std::time::Duration>,
+}
+
+impl TextSegment {
+    /// Create a segment without pause
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            pause_after: None,
+        }
+    }
+
+    /// Create a segment followed by a pause
+    pub fn with_pause(text: impl Into<String>, pause_after: std::time::Duration) -> Self {
+        Self {
+            text: text.into(),
+            pause_after: Some(pause_after),
+        }
+    }
+}
+
+impl<S: Into<String>> From<(S, Option<std::time::Duration>)> for TextSegment {
+    fn from((text, pause_after): (S, Option<std::time::Duration>)) -> Self {
+        Self {
+            text: text.into(),
+            pause_after,
+        }
+    }
+}
+
+/// Build the SSML document of segments joined by `<break>` elements, return SSML and plain text
+fn build_segments_ssml(segments: &[TextSegment], config: &SpeechConfig) -> (String, String) {
+    let mut content = String::new();
+    let mut text = String::new();
+    for segment in segments {
+        content.push_str(&render_text(&segment.text, config));
+        text.push_str(&segment.text);
+        if let Some(pause) = segment.pause_after {
+            let ms = pause.as_millis().min(crate::ssml::MAX_BREAK_MS as u128);
+            content.push_str(&format!("<break time='{}ms'/>", ms));
+        }
+    }
+    (build_ssml_content(&content, config), text)
+}
+
+/// Render plain text as SSML content, escape XML special characters
+/// and wrap words of [phonemes](SpeechConfig::phonemes) in `<phoneme>`
+fn render_text(text: &str, config: &SpeechConfig) -> String {
+    if config.phonemes.is_empty() {
+        return crate::ssml::escape(text);
+    }
+    let mut content = String::with_capacity(text.len());
+    let mut rest = text;
+    while !rest.is_empty() {
+        let word_len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '-'))
+            .unwrap_or(rest.len());
+        if word_len == 0 {
+            let c = rest.chars().next().unwrap();
+            let (c, next) = rest.split_at(c.len_utf8());
+            content.push_str(&crate::ssml::escape(c));
+            rest = next;
+            continue;
+        }
+        let word = &rest[..word_len];
+        match config.phonemes.get(word) {
+            Some(ph) => content.push_str(&format!(
+                "<phoneme alphabet='ipa' ph='{}'>{}</phoneme>",
+                crate::ssml::escape(ph),
+                crate::ssml::escape(word)
+            )),
+            None => content.push_str(&crate::ssml::escape(word)),
+        }
+        rest = &rest[word_len..];
+    }
+    content
+}
+
+/// Build the SSML document of `text` from [SpeechConfig]
+fn build_ssml(text: &str, config: &SpeechConfig) -> String {
+    build_ssml_content(&render_text(text, config), config)
+}
+
+/// Build the SSML document of rendered SSML content from [SpeechConfig]
+fn build_ssml_content(content: &str, config: &SpeechConfig) -> String {
+    let mut voice_elements = String::new();
+    for uri in &config.lexicon_uris {
+        voice_elements.push_str(&format!("<lexicon uri='{}'/>", crate::ssml::escape(uri)));
+    }
+    for silence in &config.silences {
+        voice_elements.push_str(&format!(
+            "<mstts:silence type='{}' value='{}ms'/>",
+            silence.silence_type.as_str(),
+            silence.duration.min(MAX_SILENCE).as_millis()
+        ));
+    }
+    if let Some(duration) = config.audio_duration {
+        voice_elements.push_str(&format!(
+            "<mstts:audioduration value='{}ms'/>",
+            duration.as_millis()
+        ));
+    }
+
+    let mut content = format!(
+        "<prosody pitch='{}' rate='{}' volume='{}'>{}</prosody>",
+        config.pitch, config.rate, config.volume, content,
     );
+    if config.style.is_some() || config.role.is_some() {
+        let mut attributes = String::new();
+        if let Some(ref style) = config.style {
+            attributes.push_str(&format!(" style='{}'", crate::ssml::escape(style)));
+            if let Some(style_degree) = config.style_degree {
+                // out of range degrees are rejected by the service
+                let style_degree =
+                    style_degree.clamp(*STYLE_DEGREE_RANGE.start(), *STYLE_DEGREE_RANGE.end());
+                attributes.push_str(&format!(" styledegree='{}'", style_degree));
+            }
+        }
+        if let Some(ref role) = config.role {
+            attributes.push_str(&format!(" role='{}'", crate::ssml::escape(role)));
+        }
+        content = format!(
+            "<mstts:express-as{}>{}</mstts:express-as>",
+            attributes, content
+        );
+    }
+
+    format!(
+        "<speak version='1.0' xmlns='http://www.w3.org/2001/10/synthesis' xmlns:mstts='https://www.w3.org/2001/mstts' xml:lang='{}'><voice name='{}'>{}{}</voice></speak>",
+        crate::ssml::escape(&config.lang),
+        crate::ssml::escape(&config.voice_name),
+        voice_elements,
+        content,
+    )
+}
+
+/// [request_id](SpeechConfig::request_id) of `config` or a new random one
+fn new_request_id(config: &SpeechConfig) -> String {
+    match config.request_id {
+        Some(ref request_id) => request_id.clone(),
+        None => uuid::Uuid::new_v4().simple().to_string(),
+    }
+}
+
+/// Wrap a SSML document into a `ssml` message
+fn build_ssml_message(ssml: &str, request_id: &str) -> tungstenite::Message {
     let ssml_message = format!(
         "X-RequestId:{}\r\nContent-Type:application/ssml+xml\r\nX-Timestamp:{}\r\nPath:ssml\r\n\r\n{}",
-        uuid::Uuid::new_v4().simple(),
+        request_id,
         chrono::Local::now().to_rfc2822(),
         ssml,
     );
     tungstenite::Message::Text(ssml_message)
 }
 
+/// Messages and handshake request of a synthesis, see [dry_run]
+#[derive(Debug)]
+pub struct DryRun {
+    /// websocket handshake request, including url and headers
+    pub request: http::Request<()>,
+    /// `speech.config` text message
+    pub config_message: String,
+    /// `ssml` text message
+    pub ssml_message: String,
+}
+
+/// Build the exact handshake request and messages a synthesis of `text` would send, without connecting.
+///
+/// `Sec-MS-GEC`, `ConnectionId`, `X-RequestId` and `X-Timestamp` are generated per call,
+/// so they differ from what a later real synthesis sends.
+pub fn dry_run(text: &str, config: &SpeechConfig) -> Result<DryRun> {
+    Ok(DryRun {
+        request: build_websocket_request(&connection::ConnectionConfig::default())?,
+        config_message: build_config_message(config).into_text()?,
+        ssml_message: build_ssml_message(&build_ssml(text, config), &new_request_id(config))
+            .into_text()?,
+    })
+}
+
+/// Whether the handshake was rejected with 403, e.g. an expired `Sec-MS-GEC`.
+///
+/// The server `Date` of the response is recorded, so the retry corrects the clock skew.
+fn is_forbidden(error: &Error) -> bool {
+    match error {
+        Error::TungsteniteError(tungstenite::Error::Http(response))
+            if response.status() == http::StatusCode::FORBIDDEN =>
+        {
+            if let Some(date) = response
+                .headers()
+                .get(http::header::DATE)
+                .and_then(|date| date.to_str().ok())
+            {
+                record_server_date(date);
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Retry `connect` while the handshake is rejected with 403, each attempt builds a request with a fresh `Sec-MS-GEC`
+fn retry_forbidden<T>(
+    policy: &crate::retry::RetryPolicy,
+    mut connect: impl FnMut() -> Result<T>,
+) -> Result<T> {
+    let mut retry = 0;
+    loop {
+        match connect() {
+            Err(error) if is_forbidden(&error) && retry + 1 < policy.max_attempts => {
+                std::thread::sleep(policy.backoff(retry));
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Async version of [retry_forbidden]
+async fn retry_forbidden_async<T, F, Fut>(
+    policy: &crate::retry::RetryPolicy,
+    mut connect: F,
+) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<T>>,
+{
+    let mut retry = 0;
+    loop {
+        match connect().await {
+            Err(error) if is_forbidden(&error) && retry + 1 < policy.max_attempts => {
+                async_io::Timer::after(policy.backoff(retry)).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
 type WebSocketStream<T> = tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<T>>;
 
 fn websocket_connect() -> Result<WebSocketStream<std::net::TcpStream>> {
-    let request = build_websocket_request()?;
-    let (websocket, _) = tungstenite::connect(request)?;
+    websocket_connect_with(&connection::ConnectionConfig::default())
+}
+
+fn websocket_connect_with(
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStream<std::net::TcpStream>> {
+    retry_forbidden(&config.forbidden_retry, || websocket_connect_once(config))
+}
+
+fn websocket_connect_once(
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStream<std::net::TcpStream>> {
+    use std::net::{TcpStream, ToSocketAddrs};
+    use tungstenite::handshake::HandshakeError;
+
+    let request = build_websocket_request(config)?;
+    if config.connect_timeout.is_none()
+        && config.handshake_timeout.is_none()
+        && config.tls.is_none()
+    {
+        let (websocket, _) = tungstenite::connect(request)?;
+        return Ok(websocket);
+    }
+
+    let host = request.uri().host().unwrap_or_default();
+    let port = request.uri().port_u16().unwrap_or(443);
+    let mut last_error = None;
+    let mut stream = None;
+    for addr in (host, port).to_socket_addrs()? {
+        let result = match config.connect_timeout {
+            Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match result {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(err) => last_error = Some(err),
+        }
+    }
+    let stream = match stream {
+        Some(stream) => stream,
+        None => {
+            return Err(last_error
+                .unwrap_or_else(|| std::io::ErrorKind::AddrNotAvailable.into())
+                .into())
+        }
+    };
+
+    // the clone shares the socket, to clear the timeouts after the handshake
+    let socket = stream.try_clone()?;
+    socket.set_read_timeout(config.handshake_timeout)?;
+    socket.set_write_timeout(config.handshake_timeout)?;
+    let connector = tls_connector(config)?;
+    let (websocket, _) = tungstenite::client_tls_with_config(request, stream, None, connector)
+        .map_err(|e| match e {
+            HandshakeError::Failure(e) => Error::from(e),
+            // a blocking socket with timeout reports `WouldBlock` on timeout
+            HandshakeError::Interrupted(_) => {
+                std::io::Error::from(std::io::ErrorKind::TimedOut).into()
+            }
+        })?;
+    socket.set_read_timeout(None)?;
+    socket.set_write_timeout(None)?;
     Ok(websocket)
 }
 
@@ -287,10 +1610,22 @@ fn websocket_connect_proxy(
     proxy: http::Uri,
     username: Option<&str>,
     password: Option<&str>,
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStream<ProxyStream>> {
+    retry_forbidden(&config.forbidden_retry, || {
+        websocket_connect_proxy_once(proxy.clone(), username, password, config)
+    })
+}
+
+fn websocket_connect_proxy_once(
+    proxy: http::Uri,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: &connection::ConnectionConfig,
 ) -> Result<WebSocketStream<ProxyStream>> {
     use tungstenite::handshake::HandshakeError;
 
-    let request = build_websocket_request()?;
+    let request = build_websocket_request(config)?;
     let stream: std::result::Result<ProxyStream, ProxyError> = match proxy.scheme_str() {
         Some(scheme) => match scheme.to_lowercase().as_str() {
             "socks4" | "socks4a" => {
@@ -309,28 +1644,146 @@ fn websocket_connect_proxy(
         None => http_proxy(request.uri().host().unwrap(), proxy, username, password)
             .map_err(|e| e.into()),
     };
-    let (websocket, _) = tungstenite::client_tls(request, stream?).map_err(|e| match e {
-        HandshakeError::Failure(e) => e,
-        HandshakeError::Interrupted(_) => panic!("Bug: blocking handshake not blocked"),
-    })?;
+    let connector = tls_connector(config)?;
+    let (websocket, _) = tungstenite::client_tls_with_config(request, stream?, None, connector)
+        .map_err(|e| match e {
+            HandshakeError::Failure(e) => e,
+            HandshakeError::Interrupted(_) => panic!("Bug: blocking handshake not blocked"),
+        })?;
+    Ok(websocket)
+}
+
+/// Connector of the [TlsConfig](connection::TlsConfig) of `config`, `None` for the default one
+fn tls_connector(config: &connection::ConnectionConfig) -> Result<Option<tungstenite::Connector>> {
+    let connector = config.tls.as_ref().map(|tls| tls.connector()).transpose()?;
+    Ok(connector.map(tungstenite::Connector::NativeTls))
+}
+
+/// Async connector of the [TlsConfig](connection::TlsConfig) of `config`, `None` for the default one
+fn tls_connector_async(
+    config: &connection::ConnectionConfig,
+) -> Result<Option<async_native_tls::TlsConnector>> {
+    Ok(config
+        .tls
+        .as_ref()
+        .map(|tls| tls.connector_async())
+        .transpose()?)
+}
+
+/// TLS and websocket handshakes over a stream already connected to the TTS server
+fn websocket_handshake<T: std::io::Read + std::io::Write>(
+    stream: T,
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStream<T>> {
+    use tungstenite::handshake::HandshakeError;
+
+    let request = build_websocket_request(config)?;
+    let connector = tls_connector(config)?;
+    let (websocket, _) = tungstenite::client_tls_with_config(request, stream, None, connector)
+        .map_err(|e| match e {
+            HandshakeError::Failure(e) => Error::from(e),
+            // a non-blocking stream is not supported, report it like a timed out socket
+            HandshakeError::Interrupted(_) => {
+                std::io::Error::from(std::io::ErrorKind::WouldBlock).into()
+            }
+        })?;
     Ok(websocket)
 }
 
 type WebSocketStreamAsync<T> =
     async_tungstenite::WebSocketStream<async_tungstenite::async_std::ClientStream<T>>;
 
+/// TLS and websocket handshakes over an async stream already connected to the TTS server
+async fn websocket_handshake_async<
+    T: futures_util::AsyncRead + futures_util::AsyncWrite + Unpin + Send + 'static,
+>(
+    stream: T,
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStreamAsync<T>> {
+    let request = build_websocket_request(config)?;
+    let connector = tls_connector_async(config)?;
+    let (websocket, _) =
+        async_tungstenite::async_std::client_async_tls_with_connector(request, stream, connector)
+            .await?;
+    Ok(websocket)
+}
+
 async fn websocket_connect_async() -> Result<WebSocketStreamAsync<async_std::net::TcpStream>> {
-    let request = build_websocket_request()?;
-    let (websocket, _) = async_tungstenite::async_std::connect_async(request).await?;
+    websocket_connect_with_async(&connection::ConnectionConfig::default()).await
+}
+
+async fn websocket_connect_with_async(
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStreamAsync<async_std::net::TcpStream>> {
+    retry_forbidden_async(&config.forbidden_retry, || {
+        websocket_connect_once_async(config)
+    })
+    .await
+}
+
+async fn websocket_connect_once_async(
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStreamAsync<async_std::net::TcpStream>> {
+    let request = build_websocket_request(config)?;
+    if config.connect_timeout.is_none()
+        && config.handshake_timeout.is_none()
+        && config.tls.is_none()
+    {
+        let (websocket, _) = async_tungstenite::async_std::connect_async(request).await?;
+        return Ok(websocket);
+    }
+
+    let host = request.uri().host().unwrap_or_default().to_owned();
+    let port = request.uri().port_u16().unwrap_or(443);
+    let stream = with_timeout(
+        config.connect_timeout,
+        async_std::net::TcpStream::connect((host.as_str(), port)),
+    )
+    .await??;
+    let (websocket, _) = with_timeout(
+        config.handshake_timeout,
+        async_tungstenite::async_std::client_async_tls_with_connector(
+            request,
+            stream,
+            tls_connector_async(config)?,
+        ),
+    )
+    .await??;
     Ok(websocket)
 }
 
+/// Fail with [TimedOut](std::io::ErrorKind::TimedOut) if `future` does not complete within `timeout`
+async fn with_timeout<T>(
+    timeout: Option<std::time::Duration>,
+    future: impl std::future::Future<Output = T>,
+) -> std::io::Result<T> {
+    match timeout {
+        Some(timeout) => async_std::future::timeout(timeout, future)
+            .await
+            .map_err(|_| std::io::ErrorKind::TimedOut.into()),
+        None => Ok(future.await),
+    }
+}
+
 async fn websocket_connect_proxy_async(
     proxy: http::Uri,
     username: Option<&str>,
     password: Option<&str>,
+    config: &connection::ConnectionConfig,
 ) -> Result<WebSocketStreamAsync<ProxyAsyncStream>> {
-    let request = build_websocket_request()?;
+    retry_forbidden_async(&config.forbidden_retry, || {
+        websocket_connect_proxy_once_async(proxy.clone(), username, password, config)
+    })
+    .await
+}
+
+async fn websocket_connect_proxy_once_async(
+    proxy: http::Uri,
+    username: Option<&str>,
+    password: Option<&str>,
+    config: &connection::ConnectionConfig,
+) -> Result<WebSocketStreamAsync<ProxyAsyncStream>> {
+    let request = build_websocket_request(config)?;
     let stream: std::result::Result<ProxyAsyncStream, ProxyError> = match proxy.scheme_str() {
         Some(scheme) => match scheme.to_lowercase().as_str() {
             "socks4" | "socks4a" => {
@@ -354,6 +1807,36 @@ async fn websocket_connect_proxy_async(
             .await
             .map_err(|e| e.into()),
     };
-    let (websocket, _) = async_tungstenite::async_std::client_async_tls(request, stream?).await?;
+    let (websocket, _) = with_timeout(
+        config.handshake_timeout,
+        async_tungstenite::async_std::client_async_tls_with_connector(
+            request,
+            stream?,
+            tls_connector_async(config)?,
+        ),
+    )
+    .await??;
     Ok(websocket)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ssml_message_keeps_markup() {
+        // synthesize_ssml sends the document as is
+        let ssml = "<speak version='1.0' xml:lang='en-US'><voice name='en-US-AriaNeural'>Tom &amp; <break time='1s'/>Jerry 🎉</voice></speak>";
+        let message = build_ssml_message(ssml, "id").into_text().unwrap();
+        assert!(message.ends_with(&format!("\r\n\r\n{}", ssml)));
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        let ssml = build_ssml(
+            "<b>Tom & Jerry</b>",
+            &SpeechConfig::with_voice_short_name("en-US-AriaNeural"),
+        );
+        assert!(ssml.contains("&lt;b&gt;Tom &amp; Jerry&lt;/b&gt;"));
+    }
+}
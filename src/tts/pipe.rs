@@ -0,0 +1,45 @@
+//! Named pipe output
+//!
+//! Use [open_pipe] to open a Unix FIFO or a Windows named pipe for writing,
+//! or [synthesize_to_pipe](super::client::MSEdgeTTSClient::synthesize_to_pipe) to stream audio into it directly.
+
+use crate::error::Result;
+use std::{fs::File, path::Path};
+
+/// Open a named pipe for writing.
+///
+/// On Unix, `path` is a FIFO. If `create` is true and `path` does not exist,
+/// it is created like `mkfifo`, with mode `0o666` minus the umask.  
+/// On Windows, `path` is an existing pipe like `\\.\pipe\name` created by the reading process,
+/// creating a missing pipe fails with [std::io::ErrorKind::Unsupported].
+///
+/// **Caution**: opening a FIFO blocks until another process opens it for reading.
+pub fn open_pipe(path: impl AsRef<Path>, create: bool) -> Result<File> {
+    let path = path.as_ref();
+    if create && !path.exists() {
+        create_pipe(path)?;
+    }
+    Ok(std::fs::OpenOptions::new().write(true).open(path)?)
+}
+
+#[cfg(unix)]
+fn create_pipe(path: &Path) -> Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).map_err(std::io::Error::from)?;
+    // SAFETY: `path` is a nul terminated string living across the call
+    if unsafe { libc::mkfifo(path.as_ptr(), 0o666) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().into())
+    }
+}
+
+#[cfg(not(unix))]
+fn create_pipe(path: &Path) -> Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!("can not create named pipe {}", path.display()),
+    )
+    .into())
+}
@@ -0,0 +1,152 @@
+//! Connection pool module
+//!
+//! [TTSPool] keeps warm async connections and hands them out to concurrent syntheses.
+//! [SharedTTSClient] is a cloneable handle of a pool, to share one pool across threads and tasks.
+
+use super::{
+    client::{MSEdgeTTSClientAsync, SynthesizedAudio},
+    connection::ConnectionConfig,
+    limit::RateLimit,
+    SpeechConfig,
+};
+use crate::error::Result;
+use async_lock::{futures::AcquireArc, Semaphore, SemaphoreGuardArc};
+use std::sync::{Arc, Mutex};
+
+type Client = MSEdgeTTSClientAsync<async_std::net::TcpStream>;
+
+/// Pool of async TTS clients
+///
+/// At most `size` syntheses run at the same time, further calls wait for a free connection.
+/// A client whose synthesis failed is dropped and replaced by a new connection on demand.
+pub struct TTSPool {
+    size: usize,
+    config: ConnectionConfig,
+    idle: Mutex<Vec<Client>>,
+    /// one permit per running synthesis, shared with services waiting in `poll_ready`
+    permits: Arc<Semaphore>,
+    rate_limit: Option<RateLimit>,
+    validate_idle: bool,
+}
+
+impl TTSPool {
+    /// Open `size` connections
+    pub async fn new(size: usize) -> Result<Self> {
+        Self::with_config(size, ConnectionConfig::default()).await
+    }
+
+    /// Open `size` connections with `config`
+    pub async fn with_config(size: usize, config: ConnectionConfig) -> Result<Self> {
+        let size = size.max(1);
+        let clients = futures_util::future::try_join_all(
+            (0..size).map(|_| Client::connect_with(config.clone())),
+        )
+        .await?;
+        Ok(Self {
+            size,
+            config,
+            idle: Mutex::new(clients),
+            permits: Arc::new(Semaphore::new(size)),
+            rate_limit: None,
+            validate_idle: false,
+        })
+    }
+
+    /// Delay requests of all connections according to `limit`
+    pub fn rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = Some(limit);
+        self
+    }
+
+    /// [Ping](MSEdgeTTSClientAsync::ping) idle connections before reusing them,
+    /// so a connection closed by the server while idle is replaced instead of failing the synthesis.
+    pub fn validate_idle(mut self, validate: bool) -> Self {
+        self.validate_idle = validate;
+        self
+    }
+
+    /// Maximum number of connections
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Number of open connections not in use
+    pub fn idle(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Synthesize text with a [SpeechConfig] on a pooled connection
+    pub async fn synthesize(&self, text: &str, config: &SpeechConfig) -> Result<SynthesizedAudio> {
+        let permit = self.acquire().await;
+        self.synthesize_with_permit(permit, text, config).await
+    }
+
+    /// Wait for a free connection, the permit is released when dropped
+    pub(super) fn acquire(&self) -> AcquireArc {
+        self.permits.acquire_arc()
+    }
+
+    /// Synthesize on a pooled connection while holding a permit from [acquire](Self::acquire)
+    pub(super) async fn synthesize_with_permit(
+        &self,
+        _permit: SemaphoreGuardArc,
+        text: &str,
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        if let Some(ref limit) = self.rate_limit {
+            limit.wait_async().await;
+        }
+        let mut client = loop {
+            let idle = self.idle.lock().unwrap().pop();
+            match idle {
+                Some(mut client) if self.validate_idle => {
+                    if client.ping().await.is_ok() {
+                        break client;
+                    }
+                }
+                Some(client) => break client,
+                None => break Client::connect_with(self.config.clone()).await?,
+            }
+        };
+        let result = client.synthesize(text, config).await;
+        if result.is_ok() {
+            self.idle.lock().unwrap().push(client);
+        }
+        result
+    }
+}
+
+/// Cloneable thread-safe handle of a [TTSPool]
+///
+/// Clones share the connections, e.g. one handle per web handler.
+/// With a pool of size 1, turns are serialized on one connection.
+#[derive(Clone)]
+pub struct SharedTTSClient(Arc<TTSPool>);
+
+impl SharedTTSClient {
+    /// Open a pool of `size` connections
+    pub async fn new(size: usize) -> Result<Self> {
+        Ok(TTSPool::new(size).await?.into())
+    }
+
+    /// Open a pool of `size` connections with `config`
+    pub async fn with_config(size: usize, config: ConnectionConfig) -> Result<Self> {
+        Ok(TTSPool::with_config(size, config).await?.into())
+    }
+
+    /// Synthesize text with a [SpeechConfig] on a pooled connection
+    pub async fn synthesize(&self, text: &str, config: &SpeechConfig) -> Result<SynthesizedAudio> {
+        self.0.synthesize(text, config).await
+    }
+
+    /// The shared pool
+    pub fn pool(&self) -> &TTSPool {
+        &self.0
+    }
+}
+
+impl From<TTSPool> for SharedTTSClient {
+    fn from(pool: TTSPool) -> Self {
+        Self(Arc::new(pool))
+    }
+}
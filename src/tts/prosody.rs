@@ -0,0 +1,344 @@
+//! Prosody values of [SpeechConfig](super::SpeechConfig), either relative numbers or named presets.
+//!
+//! [Display](std::fmt::Display) renders the value as it appears in the SSML `<prosody>` element,
+//! [FromStr](std::str::FromStr) parses the same syntax.
+//! Serialized as a number for relative values, which keeps configs written with older versions loadable,
+//! and as a string for named presets.
+
+use super::{
+    PITCH_PERCENT_RANGE, PITCH_RANGE, PITCH_SEMITONES_RANGE, RATE_RANGE, VOLUME_DECIBELS_RANGE,
+    VOLUME_RANGE,
+};
+use crate::error::{Error, Result};
+
+/// Pitch of the voice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "ProsodyValue", into = "ProsodyValue")]
+pub enum Pitch {
+    /// Relative change in Hz, `+10Hz`
+    Hz(i32),
+    /// Relative change in semitones, `+2st`
+    Semitones(i32),
+    /// Relative change in percent, `-10%`
+    Percent(i32),
+    XLow,
+    Low,
+    Medium,
+    High,
+    XHigh,
+    /// Default pitch of the voice
+    Default,
+}
+
+/// Speaking rate of the voice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "ProsodyValue", into = "ProsodyValue")]
+pub enum Rate {
+    /// Relative change in percent, `+10%`
+    Percent(i32),
+    /// 0.5x speed
+    XSlow,
+    /// 0.64x speed
+    Slow,
+    /// 1x speed
+    Medium,
+    /// 1.55x speed
+    Fast,
+    /// 2x speed
+    XFast,
+    /// Default rate of the voice
+    Default,
+}
+
+/// Volume of the voice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "ProsodyValue", into = "ProsodyValue")]
+pub enum Volume {
+    /// Relative change in percent, `+10%`
+    Percent(i32),
+    /// Relative change in decibels, `+6dB`
+    Decibels(i32),
+    Silent,
+    XSoft,
+    Soft,
+    Medium,
+    Loud,
+    XLoud,
+    /// Default volume of the voice
+    Default,
+}
+
+impl Default for Pitch {
+    fn default() -> Self {
+        Pitch::Hz(0)
+    }
+}
+
+impl Default for Rate {
+    fn default() -> Self {
+        Rate::Percent(0)
+    }
+}
+
+impl Default for Volume {
+    fn default() -> Self {
+        Volume::Percent(0)
+    }
+}
+
+impl From<i32> for Pitch {
+    fn from(hz: i32) -> Self {
+        Pitch::Hz(hz)
+    }
+}
+
+impl From<i32> for Rate {
+    fn from(percent: i32) -> Self {
+        Rate::Percent(percent)
+    }
+}
+
+impl From<i32> for Volume {
+    fn from(percent: i32) -> Self {
+        Volume::Percent(percent)
+    }
+}
+
+impl std::fmt::Display for Pitch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pitch::Hz(hz) => write!(f, "{:+}Hz", hz),
+            Pitch::Semitones(semitones) => write!(f, "{:+}st", semitones),
+            Pitch::Percent(percent) => write!(f, "{:+}%", percent),
+            Pitch::XLow => f.write_str("x-low"),
+            Pitch::Low => f.write_str("low"),
+            Pitch::Medium => f.write_str("medium"),
+            Pitch::High => f.write_str("high"),
+            Pitch::XHigh => f.write_str("x-high"),
+            Pitch::Default => f.write_str("default"),
+        }
+    }
+}
+
+impl std::fmt::Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rate::Percent(percent) => write!(f, "{:+}%", percent),
+            Rate::XSlow => f.write_str("x-slow"),
+            Rate::Slow => f.write_str("slow"),
+            Rate::Medium => f.write_str("medium"),
+            Rate::Fast => f.write_str("fast"),
+            Rate::XFast => f.write_str("x-fast"),
+            Rate::Default => f.write_str("default"),
+        }
+    }
+}
+
+impl std::fmt::Display for Volume {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Volume::Percent(percent) => write!(f, "{:+}%", percent),
+            Volume::Decibels(decibels) => write!(f, "{:+}dB", decibels),
+            Volume::Silent => f.write_str("silent"),
+            Volume::XSoft => f.write_str("x-soft"),
+            Volume::Soft => f.write_str("soft"),
+            Volume::Medium => f.write_str("medium"),
+            Volume::Loud => f.write_str("loud"),
+            Volume::XLoud => f.write_str("x-loud"),
+            Volume::Default => f.write_str("default"),
+        }
+    }
+}
+
+impl std::str::FromStr for Pitch {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "x-low" => Pitch::XLow,
+            "low" => Pitch::Low,
+            "medium" => Pitch::Medium,
+            "high" => Pitch::High,
+            "x-high" => Pitch::XHigh,
+            "default" => Pitch::Default,
+            _ if s.ends_with("st") => Pitch::Semitones(parse_relative("pitch", s, "st")?),
+            _ if s.ends_with('%') => Pitch::Percent(parse_relative("pitch", s, "%")?),
+            _ => Pitch::Hz(parse_relative("pitch", s, "Hz")?),
+        })
+    }
+}
+
+impl std::str::FromStr for Rate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "x-slow" => Rate::XSlow,
+            "slow" => Rate::Slow,
+            "medium" => Rate::Medium,
+            "fast" => Rate::Fast,
+            "x-fast" => Rate::XFast,
+            "default" => Rate::Default,
+            _ => Rate::Percent(parse_relative("rate", s, "%")?),
+        })
+    }
+}
+
+impl std::str::FromStr for Volume {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "silent" => Volume::Silent,
+            "x-soft" => Volume::XSoft,
+            "soft" => Volume::Soft,
+            "medium" => Volume::Medium,
+            "loud" => Volume::Loud,
+            "x-loud" => Volume::XLoud,
+            "default" => Volume::Default,
+            _ if s.ends_with("dB") => Volume::Decibels(parse_relative("volume", s, "dB")?),
+            _ => Volume::Percent(parse_relative("volume", s, "%")?),
+        })
+    }
+}
+
+impl Pitch {
+    /// Check relative values against [PITCH_RANGE], [PITCH_SEMITONES_RANGE] and [PITCH_PERCENT_RANGE]
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self {
+            Pitch::Hz(hz) => check_range("pitch", *hz, PITCH_RANGE),
+            Pitch::Semitones(semitones) => check_range("pitch", *semitones, PITCH_SEMITONES_RANGE),
+            Pitch::Percent(percent) => check_range("pitch", *percent, PITCH_PERCENT_RANGE),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Rate {
+    /// Speed multiplier of this rate, `1.0` is the natural speed of the voice
+    pub fn speed(&self) -> f64 {
+        match self {
+            // -100% is not a valid speed
+            Rate::Percent(percent) => (1.0 + *percent as f64 / 100.0).max(0.1),
+            Rate::XSlow => 0.5,
+            Rate::Slow => 0.64,
+            Rate::Medium | Rate::Default => 1.0,
+            Rate::Fast => 1.55,
+            Rate::XFast => 2.0,
+        }
+    }
+
+    /// Check relative values against [RATE_RANGE]
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self {
+            Rate::Percent(percent) => check_range("rate", *percent, RATE_RANGE),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Volume {
+    /// Check relative values against [VOLUME_RANGE] and [VOLUME_DECIBELS_RANGE]
+    pub(crate) fn validate(&self) -> Result<()> {
+        match self {
+            Volume::Percent(percent) => check_range("volume", *percent, VOLUME_RANGE),
+            Volume::Decibels(decibels) => check_range("volume", *decibels, VOLUME_DECIBELS_RANGE),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn check_range(
+    field: &'static str,
+    value: i32,
+    range: std::ops::RangeInclusive<i32>,
+) -> Result<()> {
+    if range.contains(&value) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSpeechConfig {
+            field,
+            reason: format!("{} is out of range {:?}", value, range),
+        })
+    }
+}
+
+/// Parse a relative value like `+10Hz`, `-5%`, `+2st` or a bare number
+fn parse_relative(field: &'static str, s: &str, unit: &str) -> Result<i32> {
+    s.strip_suffix(unit)
+        .unwrap_or(s)
+        .trim_start_matches('+')
+        .parse()
+        .map_err(|_| Error::InvalidSpeechConfig {
+            field,
+            reason: format!("invalid value: {}", s),
+        })
+}
+
+/// Serde representation, relative values as numbers and everything else as strings
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum ProsodyValue {
+    Number(i32),
+    Text(String),
+}
+
+impl TryFrom<ProsodyValue> for Pitch {
+    type Error = Error;
+
+    fn try_from(value: ProsodyValue) -> Result<Self> {
+        match value {
+            ProsodyValue::Number(hz) => Ok(Pitch::Hz(hz)),
+            ProsodyValue::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl TryFrom<ProsodyValue> for Rate {
+    type Error = Error;
+
+    fn try_from(value: ProsodyValue) -> Result<Self> {
+        match value {
+            ProsodyValue::Number(percent) => Ok(Rate::Percent(percent)),
+            ProsodyValue::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl TryFrom<ProsodyValue> for Volume {
+    type Error = Error;
+
+    fn try_from(value: ProsodyValue) -> Result<Self> {
+        match value {
+            ProsodyValue::Number(percent) => Ok(Volume::Percent(percent)),
+            ProsodyValue::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<Pitch> for ProsodyValue {
+    fn from(pitch: Pitch) -> Self {
+        match pitch {
+            Pitch::Hz(hz) => ProsodyValue::Number(hz),
+            pitch => ProsodyValue::Text(pitch.to_string()),
+        }
+    }
+}
+
+impl From<Rate> for ProsodyValue {
+    fn from(rate: Rate) -> Self {
+        match rate {
+            Rate::Percent(percent) => ProsodyValue::Number(percent),
+            rate => ProsodyValue::Text(rate.to_string()),
+        }
+    }
+}
+
+impl From<Volume> for ProsodyValue {
+    fn from(volume: Volume) -> Self {
+        match volume {
+            Volume::Percent(percent) => ProsodyValue::Number(percent),
+            volume => ProsodyValue::Text(volume.to_string()),
+        }
+    }
+}
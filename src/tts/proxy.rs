@@ -10,6 +10,16 @@ pub enum ProxyStream {
     TlsStream(native_tls::TlsStream<std::net::TcpStream>),
 }
 
+impl ProxyStream {
+    /// The TCP socket to the proxy server
+    pub fn get_ref(&self) -> &std::net::TcpStream {
+        match self {
+            Self::TcpStream(stream) => stream,
+            Self::TlsStream(stream) => stream.get_ref(),
+        }
+    }
+}
+
 impl std::io::Read for ProxyStream {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         match self {
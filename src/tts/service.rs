@@ -0,0 +1,113 @@
+//! tower Service module
+//!
+//! Enabled by the `tower` feature.  
+//! [MSEdgeTTSService] implements `tower::Service<SynthesisRequest>`,
+//! so synthesis composes with tower middleware such as timeouts, rate limits and load shedding.
+
+use super::{
+    client::SynthesizedAudio,
+    pool::{SharedTTSClient, TTSPool},
+    SpeechConfig,
+};
+use crate::error::{Error, Result};
+use async_lock::{futures::AcquireArc, SemaphoreGuardArc};
+use futures_util::FutureExt;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Synthesis Request
+#[derive(Debug, Clone)]
+pub struct SynthesisRequest {
+    pub text: String,
+    pub config: SpeechConfig,
+}
+
+/// Synthesis Service
+///
+/// Synthesizes on the connections of a [TTSPool].
+/// `poll_ready` waits for a free connection, so middleware such as load shedding sees a full pool as not ready.
+pub struct MSEdgeTTSService {
+    client: SharedTTSClient,
+    /// pending wait for a free connection
+    acquire: Option<Pin<Box<AcquireArc>>>,
+    /// connection reserved by `poll_ready` for the next call
+    permit: Option<SemaphoreGuardArc>,
+}
+
+impl MSEdgeTTSService {
+    /// Synthesize on the pool of `client`
+    pub fn new(client: SharedTTSClient) -> Self {
+        Self {
+            client,
+            acquire: None,
+            permit: None,
+        }
+    }
+
+    /// The shared pool
+    pub fn pool(&self) -> &TTSPool {
+        self.client.pool()
+    }
+}
+
+impl From<SharedTTSClient> for MSEdgeTTSService {
+    fn from(client: SharedTTSClient) -> Self {
+        Self::new(client)
+    }
+}
+
+impl From<TTSPool> for MSEdgeTTSService {
+    fn from(pool: TTSPool) -> Self {
+        Self::new(pool.into())
+    }
+}
+
+impl Clone for MSEdgeTTSService {
+    /// The clone shares the pool but not a connection reserved by `poll_ready`
+    fn clone(&self) -> Self {
+        Self::new(self.client.clone())
+    }
+}
+
+impl std::fmt::Debug for MSEdgeTTSService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MSEdgeTTSService")
+            .field("ready", &self.permit.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl tower_service::Service<SynthesisRequest> for MSEdgeTTSService {
+    type Response = SynthesizedAudio;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<SynthesizedAudio>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if self.permit.is_none() {
+            let pool = self.client.pool();
+            let acquire = self.acquire.get_or_insert_with(|| Box::pin(pool.acquire()));
+            let permit = std::task::ready!(acquire.poll_unpin(cx));
+            self.acquire = None;
+            self.permit = Some(permit);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: SynthesisRequest) -> Self::Future {
+        let client = self.client.clone();
+        let permit = self.permit.take();
+        Box::pin(async move {
+            let pool = client.pool();
+            // called without poll_ready, wait for a connection here
+            let permit = match permit {
+                Some(permit) => permit,
+                None => pool.acquire().await,
+            };
+            pool.synthesize_with_permit(permit, &request.text, &request.config)
+                .await
+        })
+    }
+}
@@ -0,0 +1,75 @@
+//! Boundary-based text statistics
+//!
+//! Use [text_statistics] to compute speaking pace, sentence durations and pauses from [AudioMetadata],
+//! e.g. to tune [rate](super::SpeechConfig::rate) programmatically.
+
+use super::{chapter::chapters, AudioMetadata};
+use std::time::Duration;
+
+/// gaps between two words shorter than this are not counted as pauses
+pub const PAUSE_THRESHOLD: Duration = Duration::from_millis(100);
+
+/// Text Statistics
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextStatistics {
+    /// number of words, punctuation boundaries are not counted
+    pub word_count: usize,
+    /// from the start of the first word to the end of the last word
+    pub speech_duration: Duration,
+    pub words_per_minute: f64,
+    /// duration of each sentence, from `SentenceBoundary` metadata if present,
+    /// otherwise from words split at sentence punctuation
+    pub sentence_durations: Vec<Duration>,
+    /// number of gaps between words not shorter than [PAUSE_THRESHOLD]
+    pub pause_count: usize,
+    pub total_pause: Duration,
+    pub mean_pause: Duration,
+    pub max_pause: Duration,
+}
+
+/// Compute [TextStatistics] from `WordBoundary` and `SentenceBoundary` metadata
+pub fn text_statistics(metadata: &[AudioMetadata]) -> TextStatistics {
+    let words: Vec<&AudioMetadata> = metadata
+        .iter()
+        .filter(|x| x.metadata_type.as_deref() == Some("WordBoundary"))
+        .collect();
+    let mut statistics = TextStatistics::default();
+    if words.is_empty() {
+        return statistics;
+    }
+
+    statistics.word_count = words.iter().filter(|x| !is_punctuation(x)).count();
+    statistics.speech_duration = words[words.len() - 1]
+        .end()
+        .saturating_sub(words[0].start());
+    if !statistics.speech_duration.is_zero() {
+        statistics.words_per_minute =
+            statistics.word_count as f64 * 60.0 / statistics.speech_duration.as_secs_f64();
+    }
+
+    statistics.sentence_durations = chapters(metadata)
+        .iter()
+        .map(|x| Duration::from_millis(x.end_ms.saturating_sub(x.start_ms)))
+        .collect();
+
+    for pair in words.windows(2) {
+        let pause = pair[1].start().saturating_sub(pair[0].end());
+        if pause >= PAUSE_THRESHOLD {
+            statistics.pause_count += 1;
+            statistics.total_pause += pause;
+            statistics.max_pause = statistics.max_pause.max(pause);
+        }
+    }
+    if statistics.pause_count > 0 {
+        statistics.mean_pause = statistics.total_pause / statistics.pause_count as u32;
+    }
+
+    statistics
+}
+
+fn is_punctuation(metadata: &AudioMetadata) -> bool {
+    match metadata.text {
+        Some(ref text) => !text.chars().any(|c| c.is_alphanumeric()),
+        None => true,
+    }
+}
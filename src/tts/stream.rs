@@ -2,20 +2,26 @@
 
 use super::{
     super::error::Result,
-    build_config_message, build_ssml_message, process_message,
+    build_context_message, build_segments_ssml, build_ssml, build_ssml_message, check_request,
+    connection::ConnectionConfig,
+    context::SpeechContext,
+    new_request_id, process_message,
     proxy::{ProxyAsyncStream, ProxyStream},
+    speech_context,
+    usage::UsageTracker,
     websocket_connect, websocket_connect_async, websocket_connect_proxy,
-    websocket_connect_proxy_async, AudioMetadata, ProcessedMessage, SpeechConfig, WebSocketStream,
-    WebSocketStreamAsync,
+    websocket_connect_proxy_async, AudioMetadata, ProcessedMessage, SpeechConfig, TextSegment,
+    TurnState, WebSocketStream, WebSocketStreamAsync,
 };
 use futures_util::{
     stream::{SplitSink, SplitStream},
-    AsyncRead, AsyncWrite, SinkExt, StreamExt,
+    AsyncRead, AsyncWrite, FutureExt, SinkExt, StreamExt,
 };
 use std::{
     io::{Read, Write},
-    sync::{Arc, Condvar, Mutex},
-    time::Duration,
+    ops::Deref,
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime},
 };
 
 /// Synthesized Stream Response
@@ -27,6 +33,53 @@ pub enum SynthesizedResponse {
     AudioMetadata(Vec<AudioMetadata>),
 }
 
+/// Synthesized Stream Response with receive time
+#[derive(Debug)]
+pub struct TimestampedResponse {
+    pub response: SynthesizedResponse,
+    /// wall-clock time when the response was received
+    pub received_at: SystemTime,
+    /// time elapsed since the text of current turn was sent
+    pub since_send: Option<Duration>,
+}
+
+/// Record send time of a turn and receive time of its responses, shared by sender and reader
+struct Timing {
+    sent_at: Arc<Mutex<Option<Instant>>>,
+    first_audio_latency: Option<Duration>,
+    turn_has_audio: bool,
+}
+
+impl Timing {
+    fn new(sent_at: Arc<Mutex<Option<Instant>>>) -> Self {
+        Self {
+            sent_at,
+            first_audio_latency: None,
+            turn_has_audio: false,
+        }
+    }
+
+    fn stamp(&mut self, response: SynthesizedResponse) -> TimestampedResponse {
+        let received_at = SystemTime::now();
+        let since_send = self.sent_at.lock().unwrap().map(|x| x.elapsed());
+        if let SynthesizedResponse::AudioBytes(_) = response {
+            if !self.turn_has_audio {
+                self.turn_has_audio = true;
+                self.first_audio_latency = since_send;
+            }
+        }
+        TimestampedResponse {
+            response,
+            received_at,
+            since_send,
+        }
+    }
+
+    fn end_turn(&mut self) {
+        self.turn_has_audio = false;
+    }
+}
+
 impl From<ProcessedMessage> for SynthesizedResponse {
     fn from(message: ProcessedMessage) -> Self {
         match message {
@@ -60,32 +113,42 @@ pub fn msedge_tts_split_proxy(
     username: Option<&str>,
     password: Option<&str>,
 ) -> Result<(Sender<ProxyStream>, Reader<ProxyStream>)> {
-    _msedge_tts_split(websocket_connect_proxy(proxy, username, password)?)
+    _msedge_tts_split(websocket_connect_proxy(
+        proxy,
+        username,
+        password,
+        &ConnectionConfig::default(),
+    )?)
 }
 
 fn _msedge_tts_split<T: Read + Write>(
     websocket: WebSocketStream<T>,
 ) -> Result<(Sender<T>, Reader<T>)> {
-    let websocket = Arc::new(Mutex::new(websocket));
+    let websocket = Arc::new(SharedWebSocket(Mutex::new(websocket)));
     let can_read_cvar = Arc::new((Mutex::new(false), Condvar::new()));
+    let sent_at = Arc::new(Mutex::new(None));
     let sender = Sender {
         websocket: websocket.clone(),
         can_read_cvar: can_read_cvar.clone(),
+        sent_at: sent_at.clone(),
+        sent_context: None,
     };
     let reader = Reader {
         websocket,
         can_read_cvar,
-        turn_start: false,
-        response: false,
-        turn_end: false,
+        turn_state: TurnState::default(),
+        timing: Timing::new(sent_at),
     };
     Ok((sender, reader))
 }
 
 /// Sync TTS Stream Sender
 pub struct Sender<T: Read + Write> {
-    websocket: Arc<Mutex<WebSocketStream<T>>>,
+    websocket: Arc<SharedWebSocket<T>>,
     can_read_cvar: Arc<(Mutex<bool>, Condvar)>,
+    sent_at: Arc<Mutex<Option<Instant>>>,
+    /// context of the last speech.config sent on this connection
+    sent_context: Option<SpeechContext>,
 }
 
 impl<T: Read + Write> Sender<T> {
@@ -93,23 +156,55 @@ impl<T: Read + Write> Sender<T> {
     /// **Caution**: One [send](Self::send) corresponds to multiple [read](Reader::read). Next [send](Self::send) call will block until there no data to read.
     /// [read](Reader::read) will block before you call a [send](Self::send).
     pub fn send(&mut self, text: &str, config: &SpeechConfig) -> Result<()> {
+        self.send_request(text, &build_ssml(text, config), config)
+    }
+
+    /// Synthesize text segments with pauses between them synchronously, see [send](Self::send).
+    pub fn send_segments(&mut self, segments: &[TextSegment], config: &SpeechConfig) -> Result<()> {
+        let (ssml, text) = build_segments_ssml(segments, config);
+        self.send_request(&text, &ssml, config)
+    }
+
+    /// Synthesize a complete SSML document synchronously, it may contain multiple `<voice>` elements.
+    /// Only [audio_format](SpeechConfig::audio_format) of `config` is used.
+    pub fn send_ssml(&mut self, ssml: &str, config: &SpeechConfig) -> Result<()> {
+        self.send_request(ssml, ssml, config)
+    }
+
+    fn send_request(&mut self, text: &str, ssml: &str, config: &SpeechConfig) -> Result<()> {
+        check_request(ssml, config)?;
         let (can_read, cvar) = &*self.can_read_cvar;
         let mut can_read = can_read.lock().unwrap();
         while *can_read {
             can_read = cvar.wait(can_read).unwrap();
         }
 
-        let config_message = build_config_message(config);
-        let ssml_message = build_ssml_message(text, config);
+        let context = speech_context(config);
+        let ssml_message = build_ssml_message(ssml, &new_request_id(config));
         let mut websocket = self.websocket.lock().unwrap();
-        websocket.send(config_message)?;
+        if self.sent_context.as_ref() != Some(&context) {
+            websocket.send(build_context_message(&context))?;
+            self.sent_context = Some(context);
+        }
         websocket.send(ssml_message)?;
+        UsageTracker::global().add_characters(text);
+        *self.sent_at.lock().unwrap() = Some(Instant::now());
 
         *can_read = true;
         cvar.notify_one();
         Ok(())
     }
 
+    /// Close the connection with a websocket Close frame, the [Reader] ends after the Close reply of the server.
+    ///
+    /// Dropping both halves also sends a Close frame.
+    pub fn close(&mut self) -> Result<()> {
+        let mut websocket = self.websocket.lock().unwrap();
+        websocket.close(None)?;
+        websocket.flush()?;
+        Ok(())
+    }
+
     /// Check if can send
     pub fn can_send(&self) -> bool {
         let (can_read, _) = &*self.can_read_cvar;
@@ -119,11 +214,10 @@ impl<T: Read + Write> Sender<T> {
 
 /// Sync TTS Stream Reader
 pub struct Reader<T: Read + Write> {
-    websocket: Arc<Mutex<WebSocketStream<T>>>,
+    websocket: Arc<SharedWebSocket<T>>,
     can_read_cvar: Arc<(Mutex<bool>, Condvar)>,
-    turn_start: bool,
-    response: bool,
-    turn_end: bool,
+    turn_state: TurnState,
+    timing: Timing,
 }
 
 impl<T: Read + Write> Reader<T> {
@@ -131,6 +225,11 @@ impl<T: Read + Write> Reader<T> {
     /// **Caution**: One [send](Sender::send) corresponds to multiple [read](Self::read). Next [send](Sender::send) call will block until there no data to read.
     /// [read](Self::read) will block before you call a [send](Sender::send).
     pub fn read(&mut self) -> Result<Option<SynthesizedResponse>> {
+        Ok(self.read_timestamped()?.map(|x| x.response))
+    }
+
+    /// Read Synthesized Audio synchronously with its receive time, see [read](Self::read).
+    pub fn read_timestamped(&mut self) -> Result<Option<TimestampedResponse>> {
         let (can_read, cvar) = &*self.can_read_cvar;
         let mut can_read = can_read.lock().unwrap();
         while !*can_read {
@@ -138,22 +237,22 @@ impl<T: Read + Write> Reader<T> {
         }
 
         let mut websocket = self.websocket.lock().unwrap();
-        let message = process_message(
-            websocket.read()?,
-            &mut self.turn_start,
-            &mut self.response,
-            &mut self.turn_end,
-        )?;
-
-        if self.turn_start && self.response && self.turn_end {
-            self.turn_start = false;
-            self.response = false;
-            self.turn_end = false;
+        let message = process_message(websocket.read()?, &mut self.turn_state)?;
+        let response = message.map(|message| self.timing.stamp(message.into()));
+
+        if self.turn_state.is_complete() {
+            self.turn_state = TurnState::default();
+            self.timing.end_turn();
             *can_read = false;
             cvar.notify_one();
         }
 
-        Ok(message.map(|message| message.into()))
+        Ok(response)
+    }
+
+    /// Time from [send](Sender::send) to the first audio bytes of the latest turn
+    pub fn first_audio_latency(&self) -> Option<Duration> {
+        self.timing.first_audio_latency
     }
 
     /// Check if can read
@@ -163,6 +262,25 @@ impl<T: Read + Write> Reader<T> {
     }
 }
 
+/// Websocket shared by [Sender] and [Reader], sends a best effort Close frame when the last half is dropped
+struct SharedWebSocket<T: Read + Write>(Mutex<WebSocketStream<T>>);
+
+impl<T: Read + Write> Deref for SharedWebSocket<T> {
+    type Target = Mutex<WebSocketStream<T>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: Read + Write> Drop for SharedWebSocket<T> {
+    fn drop(&mut self) {
+        let websocket = self.0.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let _ = websocket.close(None);
+        let _ = websocket.flush();
+    }
+}
+
 /// Create Async TTS Stream [SenderAsync] and [ReaderAsync]
 pub async fn msedge_tts_split_async() -> Result<(
     SenderAsync<async_std::net::TcpStream>,
@@ -186,33 +304,41 @@ pub async fn msedge_tts_split_proxy_async(
     username: Option<&str>,
     password: Option<&str>,
 ) -> Result<(SenderAsync<ProxyAsyncStream>, ReaderAsync<ProxyAsyncStream>)> {
-    _msedge_tts_split_async(websocket_connect_proxy_async(proxy, username, password).await?)
+    _msedge_tts_split_async(
+        websocket_connect_proxy_async(proxy, username, password, &ConnectionConfig::default())
+            .await?,
+    )
 }
 
 fn _msedge_tts_split_async<T: AsyncRead + AsyncWrite + Unpin>(
     websocket: WebSocketStreamAsync<T>,
 ) -> Result<(SenderAsync<T>, ReaderAsync<T>)> {
     let (sink, stream) = websocket.split();
-    let can_read = Arc::new(async_lock::Mutex::new(false));
+    let can_read = Arc::new(TurnFlag::default());
+    let sent_at = Arc::new(Mutex::new(None));
     Ok((
         SenderAsync {
             sink,
             can_read: can_read.clone(),
+            sent_at: sent_at.clone(),
+            sent_context: None,
         },
         ReaderAsync {
             stream,
             can_read,
-            turn_start: false,
-            response: false,
-            turn_end: false,
+            turn_state: TurnState::default(),
+            timing: Timing::new(sent_at),
         },
     ))
 }
 
 /// Async TTS Stream Sender
-pub struct SenderAsync<T> {
+pub struct SenderAsync<T: AsyncRead + AsyncWrite + Unpin> {
     sink: SplitSink<WebSocketStreamAsync<T>, tungstenite::Message>,
-    can_read: Arc<async_lock::Mutex<bool>>,
+    can_read: Arc<TurnFlag>,
+    sent_at: Arc<Mutex<Option<Instant>>>,
+    /// context of the last speech.config sent on this connection
+    sent_context: Option<SpeechContext>,
 }
 
 impl<T: AsyncRead + AsyncWrite + Unpin> SenderAsync<T> {
@@ -220,31 +346,69 @@ impl<T: AsyncRead + AsyncWrite + Unpin> SenderAsync<T> {
     /// **Caution**: One [send](Self::send) corresponds to multiple [read](ReaderAsync::read). Next [send](Self::send) call will block until there no data to read.
     /// [read](ReaderAsync::read) will block before you call a [send](Self::send).
     pub async fn send(&mut self, text: &str, config: &SpeechConfig) -> Result<()> {
-        while !self.can_send().await {
-            async_io::Timer::after(Duration::from_millis(1)).await;
+        self.send_request(text, &build_ssml(text, config), config)
+            .await
+    }
+
+    /// Synthesize text segments with pauses between them asynchronously, see [send](Self::send).
+    pub async fn send_segments(
+        &mut self,
+        segments: &[TextSegment],
+        config: &SpeechConfig,
+    ) -> Result<()> {
+        let (ssml, text) = build_segments_ssml(segments, config);
+        self.send_request(&text, &ssml, config).await
+    }
+
+    /// Synthesize a complete SSML document asynchronously, it may contain multiple `<voice>` elements.
+    /// Only [audio_format](SpeechConfig::audio_format) of `config` is used.
+    pub async fn send_ssml(&mut self, ssml: &str, config: &SpeechConfig) -> Result<()> {
+        self.send_request(ssml, ssml, config).await
+    }
+
+    async fn send_request(&mut self, text: &str, ssml: &str, config: &SpeechConfig) -> Result<()> {
+        check_request(ssml, config)?;
+        self.can_read.wait_for(false).await;
+        let context = speech_context(config);
+        let ssml_message = build_ssml_message(ssml, &new_request_id(config));
+        if self.sent_context.as_ref() != Some(&context) {
+            self.sink.send(build_context_message(&context)).await?;
+            self.sent_context = Some(context);
         }
-        let mut can_read = self.can_read.lock().await;
-        let config_message = build_config_message(config);
-        let ssml_message = build_ssml_message(text, config);
-        self.sink.send(config_message).await?;
         self.sink.send(ssml_message).await?;
-        *can_read = true;
+        UsageTracker::global().add_characters(text);
+        *self.sent_at.lock().unwrap() = Some(Instant::now());
+        self.can_read.set(true);
+        Ok(())
+    }
+
+    /// Close the connection with a websocket Close frame, the [ReaderAsync] ends after the Close reply of the server.
+    ///
+    /// Dropping the sender also sends a Close frame if the socket accepts it without waiting.
+    pub async fn close(&mut self) -> Result<()> {
+        self.sink.close().await?;
         Ok(())
     }
 
     /// Check if can send
     pub async fn can_send(&self) -> bool {
-        !*self.can_read.lock().await
+        !self.can_read.get()
+    }
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> Drop for SenderAsync<T> {
+    fn drop(&mut self) {
+        // drop can not await, send the Close frame only if it does not block
+        let _ = self.sink.close().now_or_never();
     }
 }
 
 /// Async TTS Stream Reader
 pub struct ReaderAsync<T> {
     stream: SplitStream<WebSocketStreamAsync<T>>,
-    can_read: Arc<async_lock::Mutex<bool>>,
-    turn_start: bool,
-    response: bool,
-    turn_end: bool,
+    can_read: Arc<TurnFlag>,
+    turn_state: TurnState,
+    timing: Timing,
 }
 
 impl<T: AsyncRead + AsyncWrite + Unpin> ReaderAsync<T> {
@@ -252,35 +416,68 @@ impl<T: AsyncRead + AsyncWrite + Unpin> ReaderAsync<T> {
     /// **Caution**: One [send](SenderAsync::send) corresponds to multiple [read](Self::read). Next [send](SenderAsync::send) call will block until there no data to read.
     /// [read](Self::read) will block before you call a [send](SenderAsync::send).
     pub async fn read(&mut self) -> Result<Option<SynthesizedResponse>> {
-        while !self.can_read().await {
-            async_io::Timer::after(Duration::from_millis(1)).await;
-        }
+        Ok(self.read_timestamped().await?.map(|x| x.response))
+    }
+
+    /// Read Synthesized Audio asynchronously with its receive time, see [read](Self::read).
+    pub async fn read_timestamped(&mut self) -> Result<Option<TimestampedResponse>> {
+        self.can_read.wait_for(true).await;
 
         let message = self.stream.next().await;
         if let Some(message) = message {
             let message = message?;
-            let message = process_message(
-                message,
-                &mut self.turn_start,
-                &mut self.response,
-                &mut self.turn_end,
-            )?;
-
-            if self.turn_start && self.response && self.turn_end {
-                self.turn_start = false;
-                self.response = false;
-                self.turn_end = false;
-                *self.can_read.lock().await = false;
+            let message = process_message(message, &mut self.turn_state)?;
+            let response = message.map(|message| self.timing.stamp(message.into()));
+
+            if self.turn_state.is_complete() {
+                self.turn_state = TurnState::default();
+                self.timing.end_turn();
+                self.can_read.set(false);
             }
 
-            Ok(message.map(|message| message.into()))
+            Ok(response)
         } else {
             Ok(None)
         }
     }
 
+    /// Time from [send](SenderAsync::send) to the first audio bytes of the latest turn
+    pub fn first_audio_latency(&self) -> Option<Duration> {
+        self.timing.first_audio_latency
+    }
+
     /// Check if can read
     pub async fn can_read(&self) -> bool {
-        *self.can_read.lock().await
+        self.can_read.get()
+    }
+}
+
+/// Whether the async reader has a turn to read, waking the half waiting for a change
+#[derive(Default)]
+struct TurnFlag {
+    can_read: Mutex<bool>,
+    changed: event_listener::Event,
+}
+
+impl TurnFlag {
+    fn get(&self) -> bool {
+        *self.can_read.lock().unwrap()
+    }
+
+    fn set(&self, can_read: bool) {
+        *self.can_read.lock().unwrap() = can_read;
+        self.changed.notify(usize::MAX);
+    }
+
+    /// Wait until the flag is `can_read`
+    async fn wait_for(&self, can_read: bool) {
+        while self.get() != can_read {
+            let listener = self.changed.listen();
+            // a change between the check and listening would be missed otherwise
+            if self.get() == can_read {
+                return;
+            }
+            listener.await;
+        }
     }
 }
@@ -0,0 +1,84 @@
+//! Tokio compatible TTS Client module
+//!
+//! Enabled by the `tokio-compat` feature.  
+//! [MSEdgeTTSClientTokio] runs the sync [MSEdgeTTSClient] on tokio blocking thread pool,
+//! so it never blocks the tokio runtime worker threads.  
+//! Must be used within a tokio runtime.
+
+use super::{
+    client::{connect, connect_proxy, MSEdgeTTSClient, SynthesizedAudio},
+    proxy::ProxyStream,
+    SpeechConfig,
+};
+use crate::error::Result;
+use std::{
+    io::{Read, Write},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// Tokio Client
+pub struct MSEdgeTTSClientTokio<T: Read + Write>(Arc<Mutex<MSEdgeTTSClient<T>>>);
+
+impl<T: Read + Write + Send + 'static> MSEdgeTTSClientTokio<T> {
+    /// Synthesize text to speech with a [SpeechConfig] on tokio blocking thread pool
+    pub async fn synthesize(&self, text: &str, config: &SpeechConfig) -> Result<SynthesizedAudio> {
+        let client = self.0.clone();
+        let text = text.to_owned();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || lock(&client).synthesize(&text, &config)).await?
+    }
+
+    /// Synthesize a complete SSML document on tokio blocking thread pool
+    pub async fn synthesize_ssml(
+        &self,
+        ssml: &str,
+        config: &SpeechConfig,
+    ) -> Result<SynthesizedAudio> {
+        let client = self.0.clone();
+        let ssml = ssml.to_owned();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || lock(&client).synthesize_ssml(&ssml, &config)).await?
+    }
+}
+
+/// Lock the client, also after a synthesis panicked while holding it.
+///
+/// A connection left in the middle of a turn is dropped by the client before the next synthesis.
+fn lock<T: Read + Write>(client: &Mutex<MSEdgeTTSClient<T>>) -> MutexGuard<'_, MSEdgeTTSClient<T>> {
+    client.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T: Read + Write> From<MSEdgeTTSClient<T>> for MSEdgeTTSClientTokio<T> {
+    fn from(client: MSEdgeTTSClient<T>) -> Self {
+        Self(Arc::new(Mutex::new(client)))
+    }
+}
+
+/// Create Tokio TTS [Client](MSEdgeTTSClientTokio)
+pub async fn connect_tokio() -> Result<MSEdgeTTSClientTokio<std::net::TcpStream>> {
+    Ok(tokio::task::spawn_blocking(connect).await??.into())
+}
+
+/// Create Tokio TTS [Client](MSEdgeTTSClientTokio) with proxy
+///
+/// The proxy protocol is specified by the URI scheme.
+///
+/// `http`: Proxy. Default when no scheme is specified.  
+/// `https`: HTTPS Proxy.  
+/// `socks4`: SOCKS4 Proxy.  
+/// `socks4a`: SOCKS4a Proxy. Proxy resolves URL hostname.  
+/// `socks5`: SOCKS5 Proxy.  
+/// `socks5h`: SOCKS5 Proxy. Proxy resolves URL hostname.  
+pub async fn connect_proxy_tokio(
+    proxy: http::Uri,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> Result<MSEdgeTTSClientTokio<ProxyStream>> {
+    let username = username.map(|x| x.to_owned());
+    let password = password.map(|x| x.to_owned());
+    Ok(tokio::task::spawn_blocking(move || {
+        connect_proxy(proxy, username.as_deref(), password.as_deref())
+    })
+    .await??
+    .into())
+}
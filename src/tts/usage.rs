@@ -0,0 +1,81 @@
+//! Global usage accounting
+//!
+//! All clients and streams of the process record their usage into [UsageTracker::global].
+//! Applications can read it with [usage](UsageTracker::usage) to enforce their own budgets,
+//! and [reset](UsageTracker::reset) it e.g. at the start of each billing period.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+static USAGE_TRACKER: UsageTracker = UsageTracker {
+    characters: AtomicU64::new(0),
+    turns: AtomicU64::new(0),
+    audio_ticks: AtomicU64::new(0),
+    bytes: AtomicU64::new(0),
+};
+
+/// Process-wide Usage Tracker
+#[derive(Debug)]
+pub struct UsageTracker {
+    characters: AtomicU64,
+    turns: AtomicU64,
+    /// 100-nanosecond ticks
+    audio_ticks: AtomicU64,
+    bytes: AtomicU64,
+}
+
+impl UsageTracker {
+    /// Get the global tracker
+    pub fn global() -> &'static Self {
+        &USAGE_TRACKER
+    }
+
+    /// Get accumulated usage
+    pub fn usage(&self) -> Usage {
+        Usage {
+            characters: self.characters.load(Ordering::Relaxed),
+            turns: self.turns.load(Ordering::Relaxed),
+            audio_duration: Duration::from_nanos(self.audio_ticks.load(Ordering::Relaxed) * 100),
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Reset all counters to zero and return the usage before reset
+    pub fn reset(&self) -> Usage {
+        Usage {
+            characters: self.characters.swap(0, Ordering::Relaxed),
+            turns: self.turns.swap(0, Ordering::Relaxed),
+            audio_duration: Duration::from_nanos(self.audio_ticks.swap(0, Ordering::Relaxed) * 100),
+            bytes: self.bytes.swap(0, Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn add_characters(&self, text: &str) {
+        self.characters
+            .fetch_add(text.chars().count() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_turn(&self, audio_ticks: u64) {
+        self.turns.fetch_add(1, Ordering::Relaxed);
+        self.audio_ticks.fetch_add(audio_ticks, Ordering::Relaxed);
+    }
+}
+
+/// Accumulated Usage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// characters of submitted text
+    pub characters: u64,
+    /// completed synthesis turns
+    pub turns: u64,
+    /// duration of synthesized audio, from audio boundary metadata
+    pub audio_duration: Duration,
+    /// bytes of all received websocket messages
+    pub bytes: u64,
+}
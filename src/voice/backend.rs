@@ -0,0 +1,267 @@
+//! HTTP backends of voice list requests
+//!
+//! `http-ureq` takes precedence over `http-reqwest`, which takes precedence over the default `http-isahc`,
+//! so enabling a lightweight backend works without disabling default features.
+//! Backends without their own async support run the blocking request on the async-std blocking thread pool.
+
+use super::VoicesListOptions;
+use crate::{constants, error::Result};
+
+/// Headers sent by MS Edge
+const HEADERS: [(&str, &str); 7] = [
+    ("Sec-CH-UA", constants::SEC_CH_UA),
+    ("Sec-CH-UA-Mobile", constants::SEC_CH_UA_MOBILE),
+    ("User-Agent", constants::USER_AGENT),
+    ("Sec-CH-UA-Platform", constants::SEC_CH_UA_PLATFORM),
+    ("Sec-Fetch-Site", constants::SEC_FETCH_SITE),
+    ("Sec-Fetch-Mode", constants::SEC_FETCH_MODE),
+    ("Sec-Fetch-Dest", constants::SEC_FETCH_DEST),
+];
+
+/// Backend independent response
+pub(super) struct Response {
+    pub status: u16,
+    // only the disk cache reads it
+    #[cfg_attr(
+        not(all(
+            feature = "cache",
+            any(
+                feature = "http-isahc",
+                feature = "http-ureq",
+                feature = "http-reqwest"
+            )
+        )),
+        allow(dead_code)
+    )]
+    pub etag: Option<String>,
+    /// server `Date` header, to correct the clock skew of `Sec-MS-GEC`
+    pub date: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[cfg(feature = "http-ureq")]
+pub(super) fn get(options: &VoicesListOptions, if_none_match: Option<&str>) -> Result<Response> {
+    let mut builder = ureq::AgentBuilder::new();
+    if let Some(ref proxy) = options.proxy {
+        let proxy = ureq::Proxy::new(proxy.url_with_credentials()).map_err(Box::new)?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.timeout_connect(timeout);
+    }
+    let mut request = builder.build().get(&options.url);
+    for (name, value) in HEADERS {
+        request = request.set(name, value);
+    }
+    if let Some(etag) = if_none_match {
+        request = request.set("If-None-Match", etag);
+    }
+    let response = match request.call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(error) => return Err(Box::new(error).into()),
+    };
+    let status = response.status();
+    let etag = response.header("ETag").map(|etag| etag.to_owned());
+    let date = response.header("Date").map(|date| date.to_owned());
+    let mut body = Vec::new();
+    std::io::Read::read_to_end(&mut response.into_reader(), &mut body)?;
+    Ok(Response {
+        status,
+        etag,
+        date,
+        body,
+    })
+}
+
+#[cfg(all(feature = "http-reqwest", not(feature = "http-ureq")))]
+pub(super) fn get(options: &VoicesListOptions, if_none_match: Option<&str>) -> Result<Response> {
+    let mut builder = reqwest::blocking::Client::builder();
+    if let Some(ref proxy) = options.proxy {
+        let mut reqwest_proxy = reqwest::Proxy::all(&proxy.url)?;
+        if let (Some(username), Some(password)) = (&proxy.username, &proxy.password) {
+            reqwest_proxy = reqwest_proxy.basic_auth(username, password);
+        }
+        builder = builder.proxy(reqwest_proxy);
+    }
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    let mut request = builder.build()?.get(&options.url);
+    for (name, value) in HEADERS {
+        request = request.header(name, value);
+    }
+    if let Some(etag) = if_none_match {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let response = request.send()?;
+    let status = response.status().as_u16();
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(|etag| etag.to_owned());
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|date| date.to_str().ok())
+        .map(|date| date.to_owned());
+    let body = response.bytes()?.to_vec();
+    Ok(Response {
+        status,
+        etag,
+        date,
+        body,
+    })
+}
+
+#[cfg(any(feature = "http-ureq", feature = "http-reqwest"))]
+pub(super) async fn get_async(
+    options: &VoicesListOptions,
+    if_none_match: Option<&str>,
+) -> Result<Response> {
+    let options = options.clone();
+    let if_none_match = if_none_match.map(|etag| etag.to_owned());
+    async_std::task::spawn_blocking(move || get(&options, if_none_match.as_deref())).await
+}
+
+/// Request on the tokio runtime with a shared connection pool, unless proxy or connect timeout needs a dedicated client
+#[cfg(feature = "tokio")]
+pub(super) async fn get_tokio(options: &VoicesListOptions) -> Result<Response> {
+    static CLIENT: std::sync::OnceLock<reqwest::Client> = std::sync::OnceLock::new();
+    let client = if options.proxy.is_none() && options.connect_timeout.is_none() {
+        CLIENT.get_or_init(reqwest::Client::new).clone()
+    } else {
+        let mut builder = reqwest::Client::builder();
+        if let Some(ref proxy) = options.proxy {
+            let mut reqwest_proxy = reqwest::Proxy::all(&proxy.url)?;
+            if let (Some(username), Some(password)) = (&proxy.username, &proxy.password) {
+                reqwest_proxy = reqwest_proxy.basic_auth(username, password);
+            }
+            builder = builder.proxy(reqwest_proxy);
+        }
+        if let Some(timeout) = options.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        builder.build()?
+    };
+    let mut request = client.get(&options.url);
+    for (name, value) in HEADERS {
+        request = request.header(name, value);
+    }
+    if let Some(timeout) = options.timeout {
+        request = request.timeout(timeout);
+    }
+    let response = request.send().await?;
+    let status = response.status().as_u16();
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .map(|etag| etag.to_owned());
+    let date = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|date| date.to_str().ok())
+        .map(|date| date.to_owned());
+    let body = response.bytes().await?.to_vec();
+    Ok(Response {
+        status,
+        etag,
+        date,
+        body,
+    })
+}
+
+#[cfg(all(
+    feature = "http-isahc",
+    not(any(feature = "http-ureq", feature = "http-reqwest"))
+))]
+pub(super) fn get(options: &VoicesListOptions, if_none_match: Option<&str>) -> Result<Response> {
+    use isahc::{ReadResponseExt, RequestExt};
+    let mut response = build_isahc_request(options, if_none_match)?.send()?;
+    let body = response.bytes()?;
+    Ok(isahc_response(&response, body))
+}
+
+#[cfg(all(
+    feature = "http-isahc",
+    not(any(feature = "http-ureq", feature = "http-reqwest"))
+))]
+pub(super) async fn get_async(
+    options: &VoicesListOptions,
+    if_none_match: Option<&str>,
+) -> Result<Response> {
+    use isahc::{AsyncReadResponseExt, RequestExt};
+    let mut response = build_isahc_request(options, if_none_match)?
+        .send_async()
+        .await?;
+    let body = response.bytes().await?;
+    Ok(isahc_response(&response, body))
+}
+
+#[cfg(all(
+    feature = "http-isahc",
+    not(any(feature = "http-ureq", feature = "http-reqwest"))
+))]
+fn isahc_response<T>(response: &isahc::Response<T>, body: Vec<u8>) -> Response {
+    Response {
+        status: response.status().as_u16(),
+        etag: response
+            .headers()
+            .get(isahc::http::header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(|etag| etag.to_owned()),
+        date: response
+            .headers()
+            .get(isahc::http::header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .map(|date| date.to_owned()),
+        body,
+    }
+}
+
+#[cfg(all(
+    feature = "http-isahc",
+    not(any(feature = "http-ureq", feature = "http-reqwest"))
+))]
+fn build_isahc_request(
+    options: &VoicesListOptions,
+    if_none_match: Option<&str>,
+) -> Result<isahc::Request<()>> {
+    use isahc::config::Configurable;
+    let mut builder = isahc::Request::get(&options.url);
+    for (name, value) in HEADERS {
+        builder = builder.header(name, value);
+    }
+    if let Some(etag) = if_none_match {
+        builder = builder.header(isahc::http::header::IF_NONE_MATCH, etag);
+    }
+
+    if let Some(ref proxy) = options.proxy {
+        let uri = proxy
+            .url
+            .parse::<isahc::http::Uri>()
+            .map_err(isahc::http::Error::from)
+            .map_err(isahc::Error::from)?;
+        builder = builder.proxy(Some(uri));
+        if let (Some(username), Some(password)) = (&proxy.username, &proxy.password) {
+            builder = builder.proxy_authentication(isahc::auth::Authentication::basic());
+            builder = builder.proxy_credentials(isahc::auth::Credentials::new(username, password));
+        }
+    }
+    if let Some(timeout) = options.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(timeout) = options.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+
+    Ok(builder.body(()).map_err(isahc::Error::from)?)
+}